@@ -1,157 +1,592 @@
 use chrono::DateTime;
 use chrono::Datelike;
+use chrono::Days;
 use chrono::Local;
+use chrono::Months;
 use chrono::NaiveDate;
-use fltk::{app, draw, enums::*, menu, prelude::*, table, window};
+use chrono::Weekday;
+use crate::events::EventStore;
+use crate::locale::CalendarLocale;
+use fltk::{app, draw, enums::*, group, menu, misc, prelude::*, table, window};
+use std::ffi::{CStr, CString};
 use std::{cell::RefCell, rc::Rc};
 
-/// Defines a calendar dialog
-pub struct Calendar {
-    wind: window::Window,
+const FIRST_YEAR: i32 = 1900;
+const LAST_YEAR: i32 = 2100;
+
+// the month being shown, the selected day and which days can be picked
+struct State {
+    year: i32,
+    month: u32,
+    date: Option<NaiveDate>,
+    range_mode: bool,
+    // the first pick of a range, waiting for the second one
+    anchor: Option<NaiveDate>,
+    range: Option<(NaiveDate, NaiveDate)>,
+    min_date: Option<NaiveDate>,
+    max_date: Option<NaiveDate>,
+    disabled: Option<Box<dyn Fn(NaiveDate) -> bool>>,
+    locale: CalendarLocale,
+    events: EventStore,
+    // the day under the mouse, whose events are shown in the tooltip
+    hover: Option<NaiveDate>,
+    // the text of that tooltip, which FLTK points to rather than copying
+    tooltip: CString,
+}
+
+impl State {
+    // the number of leading columns holding week numbers
+    fn week_cols(&self) -> i32 {
+        self.locale.week_numbers() as i32
+    }
+
+    // the date shown in a given cell, or None for the week number column
+    fn cell_date(&self, row: i32, col: i32) -> Option<NaiveDate> {
+        let col = col - self.week_cols();
+        if col < 0 {
+            None
+        } else {
+            let first_weekday = self.locale.first_weekday();
+            Some(cell_date(self.year, self.month, first_weekday, row, col))
+        }
+    }
+
+    // the ISO week of a row, i.e. the week its Thursday belongs to
+    fn week_of_row(&self, row: i32) -> u32 {
+        let first_weekday = self.locale.first_weekday();
+        (0..7)
+            .map(|col| cell_date(self.year, self.month, first_weekday, row, col))
+            .find(|date| date.weekday() == Weekday::Thu)
+            .map(|date| date.iso_week().week())
+            .unwrap_or_default()
+    }
+
+    // whether a date lies within the min and max dates
+    fn in_bounds(&self, date: NaiveDate) -> bool {
+        self.min_date.is_none_or(|min| date >= min) && self.max_date.is_none_or(|max| date <= max)
+    }
+
+    // whether a date can be picked
+    fn is_enabled(&self, date: NaiveDate) -> bool {
+        self.in_bounds(date) && !self.disabled.as_ref().is_some_and(|f| f(date))
+    }
+
+    // apply a step until it lands on a day that can be picked
+    fn next_enabled(
+        &self,
+        from: NaiveDate,
+        step: fn(NaiveDate) -> Option<NaiveDate>,
+    ) -> Option<NaiveDate> {
+        let mut next = step(from)?;
+        for _ in 0..366 {
+            if self.is_enabled(next) {
+                return Some(next);
+            }
+            if !self.in_bounds(next) {
+                break;
+            }
+            next = step(next)?;
+        }
+        None
+    }
+}
+
+type Callback = Rc<RefCell<Option<Box<dyn FnMut(&mut CalendarWidget)>>>>;
+
+/// Defines an embeddable calendar widget
+#[derive(Clone)]
+pub struct CalendarWidget {
+    flex: group::Flex,
     table: table::TableRow,
     month_choice: menu::Choice,
     year_choice: menu::Choice,
+    state: Rc<RefCell<State>>,
+    cb: Callback,
 }
 
-impl Calendar {
-    /// Creates a new calendar dialog
-    pub fn new(x: i32, y: i32) -> Self {
+impl Default for CalendarWidget {
+    fn default() -> Self {
+        CalendarWidget::new(0, 0, 0, 0, None)
+    }
+}
+
+impl CalendarWidget {
+    /// Creates a new calendar widget showing the current month
+    pub fn new<L: Into<Option<&'static str>>>(x: i32, y: i32, w: i32, h: i32, label: L) -> Self {
         // get today's date
         let local: DateTime<Local> = Local::now();
-        let curr = (local.month() - 1) as i32;
-        let curr_year = local.year();
-        // create window with month and year choice widgets
-        let mut wind = window::Window::new(x, y, 400, 300, "Calendar");
-        let mut month_choice = menu::Choice::new(100, 5, 80, 40, "");
-        month_choice.add_choice("Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec");
-        month_choice.set_value(curr);
-        let mut year_choice = menu::Choice::new(220, 5, 80, 40, "");
-        for i in 1900..curr_year + 1 {
+        let state = Rc::from(RefCell::from(State {
+            year: local.year().clamp(FIRST_YEAR, LAST_YEAR),
+            month: local.month(),
+            date: None,
+            range_mode: false,
+            anchor: None,
+            range: None,
+            min_date: None,
+            max_date: None,
+            disabled: None,
+            locale: CalendarLocale::default(),
+            events: EventStore::new(),
+            hover: None,
+            tooltip: CString::default(),
+        }));
+        let mut flex = group::Flex::new(x, y, w, h, label).column();
+        // month and year choice widgets
+        let mut header = group::Flex::default().row();
+        let month_choice = menu::Choice::default();
+        let mut year_choice = menu::Choice::default();
+        for i in FIRST_YEAR..LAST_YEAR + 1 {
             year_choice.add_choice(&format!("{}", i));
         }
-        year_choice.set_value(curr_year - 1900);
+        header.end();
+        flex.fixed(&header, 30);
         // Create a table with the days of the selected month
-        let mut table = table::TableRow::new(5, 50, 390, 250, "");
-        table.set_type(table::TableRowSelectMode::Single);
-        table.set_rows(5);
-        table.set_cols(7);
+        let mut table = table::TableRow::default();
+        table.set_type(table::TableRowSelectMode::None);
         table.set_col_header(true);
-        table.set_col_width_all(table.width() / 7);
-        table.set_row_height_all(table.height() / 4 - table.col_header_height());
         table.end();
-        wind.make_modal(true);
-        wind.end();
-        wind.show();
+        flex.end();
 
-        let curr = Rc::from(RefCell::from(curr + 1));
-        let curr_year = Rc::from(RefCell::from(curr_year));
+        let mut s = Self {
+            flex,
+            table,
+            month_choice,
+            year_choice,
+            state,
+            cb: Rc::from(RefCell::from(None)),
+        };
+        s.fill_month_choice();
+        s.update_view();
 
-        let curr_rc = curr.clone();
-        let curr_year_rc = curr_year.clone();
-        table.draw_cell(move |t, ctx, row, col, x, y, w, h| match ctx {
+        let state = s.state.clone();
+        s.table.draw_cell(move |_, ctx, row, col, x, y, w, h| match ctx {
             table::TableContext::StartPage => draw::set_font(Font::Helvetica, 14),
             table::TableContext::ColHeader => {
-                let day = match col + 1 {
-                    1 => "Mon",
-                    2 => "Tue",
-                    3 => "Wed",
-                    4 => "Thu",
-                    5 => "Fri",
-                    6 => "Sat",
-                    7 => "Sun",
-                    _ => unreachable!(),
+                let state = state.borrow();
+                let txt = match state.cell_date(0, col) {
+                    Some(date) => state.locale.weekday_name(date.weekday()),
+                    None => state.locale.week_label(),
                 };
-                draw_header(day, x, y, w, h)
+                draw_header(txt, x, y, w, h)
             }
             table::TableContext::Cell => {
-                let day = row * 7 + col + 1;
-                let max_days = match *curr_rc.borrow() {
-                    1 => 31,
-                    2 => {
-                        if *curr_year_rc.borrow() % 4 == 0 {
-                            29
-                        } else {
-                            28
-                        }
+                let state = state.borrow();
+                let Some(date) = state.cell_date(row, col) else {
+                    draw_header(&state.week_of_row(row).to_string(), x, y, w, h);
+                    return;
+                };
+                let (in_range, endpoint) = match (state.anchor, state.range) {
+                    (Some(anchor), _) => (false, date == anchor),
+                    (None, Some((start, end))) => {
+                        (date > start && date < end, date == start || date == end)
                     }
-                    3 => 31,
-                    4 => 30,
-                    5 => 31,
-                    6 => 30,
-                    7 => 31,
-                    8 => 31,
-                    9 => 30,
-                    10 => 31,
-                    11 => 30,
-                    12 => 31,
-                    _ => unreachable!(),
+                    _ => (false, false),
                 };
-                if day < (max_days + 1) {
-                    draw_data(day, x, y, w, h, t.is_selected(row, col));
-                }
+                let style = CellStyle {
+                    selected: endpoint || state.date == Some(date),
+                    in_range,
+                    faded: date.month() != state.month,
+                    disabled: !state.is_enabled(date),
+                    events: state.events.on(date).len(),
+                };
+                draw_data(date.day(), x, y, w, h, &style);
             }
             _ => (),
         });
 
-        let curr_rc = curr.clone();
+        // fit the rows and columns to the table's new size
+        s.table.resize_callback({
+            let state = s.state.clone();
+            move |t, _, _, _, _| {
+                layout_rows(t, &state.borrow());
+            }
+        });
+
         // redraw table when the month changes
-        month_choice.set_callback(move |c| {
-            *curr_rc.borrow_mut() = c.value() + 1;
-            c.parent().unwrap().redraw();
+        s.month_choice.set_callback({
+            let mut s = s.clone();
+            move |c| {
+                s.state.borrow_mut().month = c.value() as u32 + 1;
+                s.update_view();
+            }
         });
 
-        let curr_year_rc = curr_year.clone();
         // redraw table when the year changes
-        year_choice.set_callback(move |c| {
-            *curr_year_rc.borrow_mut() = c.value() + 1900;
-            c.parent().unwrap().redraw();
+        s.year_choice.set_callback({
+            let mut s = s.clone();
+            move |c| {
+                s.state.borrow_mut().year = c.value() + FIRST_YEAR;
+                s.update_view();
+            }
         });
 
-        // choose the day by double clicking a cell
-        table.handle(|t, ev| {
-            if ev == Event::Push && app::event_clicks() {
-                t.top_window().unwrap().hide();
-                true
-            } else {
-                false
+        // choose the day by clicking a cell, or move around using the keyboard
+        s.table.handle({
+            let mut s = s.clone();
+            move |t, ev| match ev {
+                Event::Focus | Event::Unfocus => true,
+                // list the events of the hovered day in the tooltip
+                Event::Enter | Event::Move | Event::Leave => {
+                    let cell = match t.cursor2rowcol() {
+                        Some((table::TableContext::Cell, row, col, _)) if ev != Event::Leave => {
+                            s.state.borrow().cell_date(row, col).map(|d| (d, (row, col)))
+                        }
+                        _ => None,
+                    };
+                    let date = cell.map(|(date, _)| date);
+                    let mut state = s.state.borrow_mut();
+                    if state.hover != date {
+                        state.hover = date;
+                        let tip = date
+                            .filter(|d| !state.events.on(*d).is_empty())
+                            .map(|d| {
+                                let mut tip =
+                                    format!("{} {}", d.day(), state.locale.month_name(d.month()));
+                                for appointment in state.events.on(d) {
+                                    tip += &format!("\n{}", appointment);
+                                }
+                                tip
+                            })
+                            .unwrap_or_default();
+                        // the tooltip is moved to the cell, replacing one already showing,
+                        // and an empty one hides it
+                        let (x, y, w, h) = cell
+                            .and_then(|(_, (row, col))| {
+                                t.find_cell(table::TableContext::Cell, row, col)
+                            })
+                            .map_or((0, 0, t.w(), t.h()), |(x, y, w, h)| {
+                                (x - t.x(), y - t.y(), w, h)
+                            });
+                        let text = CString::new(tip.replace('\0', "")).unwrap_or_default();
+                        // SAFETY: the state keeps the text until the tooltip is moved to the
+                        // next one, and the buffer doesn't move along with the CString
+                        let tip: &'static CStr = unsafe { &*(text.as_c_str() as *const CStr) };
+                        misc::Tooltip::enter_area(&*t, x, y, w, h, tip);
+                        state.tooltip = text;
+                    }
+                    ev == Event::Enter
+                }
+                Event::Push => {
+                    t.take_focus().ok();
+                    if let Some((table::TableContext::Cell, row, col, _)) = t.cursor2rowcol() {
+                        let date = {
+                            let state = s.state.borrow();
+                            state.cell_date(row, col).filter(|d| state.is_enabled(*d))
+                        };
+                        if let Some(date) = date {
+                            s.pick(date);
+                        }
+                        true
+                    } else {
+                        false
+                    }
+                }
+                Event::KeyDown => {
+                    let curr = s.state.borrow().date.unwrap_or_else(|| {
+                        let state = s.state.borrow();
+                        first_of_month(state.year, state.month)
+                    });
+                    let step: fn(NaiveDate) -> Option<NaiveDate> = match app::event_key() {
+                        Key::Left => |d| d.checked_sub_days(Days::new(1)),
+                        Key::Right => |d| d.checked_add_days(Days::new(1)),
+                        Key::Up => |d| d.checked_sub_days(Days::new(7)),
+                        Key::Down => |d| d.checked_add_days(Days::new(7)),
+                        Key::PageUp => |d| d.checked_sub_months(Months::new(1)),
+                        Key::PageDown => |d| d.checked_add_months(Months::new(1)),
+                        Key::Enter | Key::KPEnter => {
+                            if s.state.borrow().is_enabled(curr) {
+                                s.pick(curr);
+                            }
+                            return true;
+                        }
+                        _ => return false,
+                    };
+                    let next = s.state.borrow().next_enabled(curr, step);
+                    if let Some(next) = next {
+                        s.select(next);
+                    }
+                    true
+                }
+                _ => false,
             }
         });
 
-        // Keep the window shown awaiting input
-        while wind.shown() {
-            app::wait();
+        s
+    }
+
+    /// Set the selected date and show its month, doesn't trigger the callback.
+    /// Returns false, leaving the date unchanged, for a year the calendar can't show
+    pub fn set_date(&mut self, date: NaiveDate) -> bool {
+        if !in_years(date) {
+            return false;
+        }
+        {
+            let mut state = self.state.borrow_mut();
+            state.year = date.year();
+            state.month = date.month();
+            state.date = Some(date);
         }
+        self.update_view();
+        true
+    }
 
-        Self {
-            wind,
-            table,
-            month_choice,
-            year_choice,
+    /// Get the selected date
+    pub fn date(&self) -> Option<NaiveDate> {
+        self.state.borrow().date
+    }
+
+    /// Pick a start and an end date instead of a single date:
+    /// the first click anchors the range and the second one extends it
+    pub fn set_range_mode(&mut self, flag: bool) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.range_mode = flag;
+            state.anchor = None;
+            state.range = None;
         }
+        self.table.redraw();
+    }
+
+    /// Check whether the widget picks date ranges
+    pub fn range_mode(&self) -> bool {
+        self.state.borrow().range_mode
+    }
+
+    /// Get the picked range, once both of its ends were chosen
+    pub fn range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        self.state.borrow().range
+    }
+
+    /// Set the earliest date that can be picked, `None` removes the bound
+    pub fn set_min_date<D: Into<Option<NaiveDate>>>(&mut self, date: D) {
+        self.state.borrow_mut().min_date = date.into();
+        self.table.redraw();
+    }
+
+    /// Set the latest date that can be picked, `None` removes the bound
+    pub fn set_max_date<D: Into<Option<NaiveDate>>>(&mut self, date: D) {
+        self.state.borrow_mut().max_date = date.into();
+        self.table.redraw();
+    }
+
+    /// Set a predicate which disables the days it returns true for,
+    /// e.g. weekends and holidays
+    pub fn set_disabled<F: Fn(NaiveDate) -> bool + 'static>(&mut self, f: F) {
+        self.state.borrow_mut().disabled = Some(Box::new(f));
+        self.table.redraw();
+    }
+
+    /// Set the locale used to name and lay out the days
+    pub fn set_locale(&mut self, locale: CalendarLocale) {
+        self.state.borrow_mut().locale = locale;
+        self.fill_month_choice();
+        self.update_view();
+    }
+
+    /// Get the locale used to name and lay out the days
+    pub fn locale(&self) -> CalendarLocale {
+        self.state.borrow().locale.clone()
+    }
+
+    /// Set the appointments marked on the calendar
+    pub fn set_events(&mut self, events: EventStore) {
+        self.state.borrow_mut().events = events;
+        self.table.redraw();
+    }
+
+    /// Set a callback which is triggered when the user changes the selection,
+    /// or confirms it with Enter or a click
+    pub fn set_callback<F: FnMut(&mut Self) + 'static>(&mut self, cb: F) {
+        *self.cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    // pick a date, anchoring or extending the range in range mode
+    fn pick(&mut self, date: NaiveDate) {
+        if !in_years(date) {
+            return;
+        }
+        {
+            let mut state = self.state.borrow_mut();
+            if state.range_mode {
+                if let Some(anchor) = state.anchor.take() {
+                    state.range = Some((anchor.min(date), anchor.max(date)));
+                } else {
+                    state.anchor = Some(date);
+                    state.range = None;
+                }
+            }
+        }
+        self.select(date);
+    }
+
+    // select a date on behalf of the user and notify the callback
+    fn select(&mut self, date: NaiveDate) {
+        if !self.set_date(date) {
+            return;
+        }
+        if let Some(cb) = self.cb.clone().borrow_mut().as_mut() {
+            cb(self);
+        }
+    }
+
+    // fill the month choice with the locale's month names
+    fn fill_month_choice(&mut self) {
+        let names = {
+            let state = self.state.borrow();
+            (1..13)
+                .map(|month| state.locale.month_name(month).replace('|', ""))
+                .collect::<Vec<_>>()
+        };
+        self.month_choice.clear();
+        self.month_choice.add_choice(&names.join("|"));
+    }
+
+    // sync the choices and the table rows with the shown month
+    fn update_view(&mut self) {
+        let state = self.state.borrow();
+        self.month_choice.set_value(state.month as i32 - 1);
+        self.year_choice.set_value(state.year - FIRST_YEAR);
+        layout_rows(&mut self.table, &state);
+        self.table.redraw();
+    }
+}
+
+fltk::widget_extends!(CalendarWidget, group::Flex, flex);
+
+/// Defines a calendar dialog
+pub struct Calendar {
+    wind: window::Window,
+    cal: CalendarWidget,
+}
+
+impl Calendar {
+    /// Creates a new calendar dialog
+    pub fn new(x: i32, y: i32) -> Self {
+        let mut s = Self::dialog(x, y);
+        s.popup();
+        s
     }
     /// Creates a new calendar widget with a default position
     pub fn default() -> Self {
-        let mut s = Self::new(0, 0);
+        let mut s = Self::dialog(0, 0);
         s.wind.free_position();
+        s.popup();
         s
     }
+    /// Creates a calendar dialog without showing it,
+    /// so that it can be configured before calling `popup`
+    pub fn dialog(x: i32, y: i32) -> Self {
+        let mut wind = window::Window::new(x, y, 400, 300, "Calendar");
+        let mut cal = CalendarWidget::new(5, 5, 390, 290, None);
+        wind.make_modal(true);
+        wind.end();
+        // choose the day by double clicking a cell or pressing Enter,
+        // in range mode the dialog closes once the range is complete
+        cal.set_callback({
+            let mut wind = wind.clone();
+            move |c| {
+                let confirmed = match app::event() {
+                    Event::Push => c.range_mode() || app::event_clicks(),
+                    Event::KeyDown => matches!(app::event_key(), Key::Enter | Key::KPEnter),
+                    _ => false,
+                };
+                if confirmed && (!c.range_mode() || c.range().is_some()) {
+                    wind.hide();
+                }
+            }
+        });
+        Self { wind, cal }
+    }
+    /// Shows the dialog and waits for it to be closed
+    pub fn popup(&mut self) {
+        self.wind.show();
+        // Keep the window shown awaiting input
+        while self.wind.shown() {
+            app::wait();
+        }
+    }
+    /// Pick a start and an end date instead of a single date
+    pub fn set_range_mode(&mut self, flag: bool) {
+        self.cal.set_range_mode(flag);
+    }
+    /// Set the earliest date that can be picked
+    pub fn set_min_date<D: Into<Option<NaiveDate>>>(&mut self, date: D) {
+        self.cal.set_min_date(date);
+    }
+    /// Set the latest date that can be picked
+    pub fn set_max_date<D: Into<Option<NaiveDate>>>(&mut self, date: D) {
+        self.cal.set_max_date(date);
+    }
+    /// Disable the days the predicate returns true for
+    pub fn set_disabled<F: Fn(NaiveDate) -> bool + 'static>(&mut self, f: F) {
+        self.cal.set_disabled(f);
+    }
+    /// Set the locale used to name and lay out the days
+    pub fn set_locale(&mut self, locale: CalendarLocale) {
+        self.cal.set_locale(locale);
+    }
+    /// Set the appointments marked on the calendar
+    pub fn set_events(&mut self, events: EventStore) {
+        self.cal.set_events(events);
+    }
     /// Get the date selected by the calendar dialog
     pub fn get_date(&self) -> Option<chrono::naive::NaiveDate> {
-        // get table selection
-        let (r, c, _, _) = self.table.get_selection();
-        if r == -1 || c == -1 {
-            None
-        } else {
-            let day = r * 7 + c + 1;
-            NaiveDate::from_ymd_opt(
-                self.year_choice.value() + 1900,
-                self.month_choice.value() as u32 + 1,
-                day as u32,
-            )
-        }
+        self.cal.date()
+    }
+    /// Get the range selected by the calendar dialog in range mode
+    pub fn get_range(&self) -> Option<(NaiveDate, NaiveDate)> {
+        self.cal.range()
     }
 }
 
+// the 1st of the given month
+fn first_of_month(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap()
+}
+
+// number of days in the given month, leap years included
+fn days_in_month(year: i32, month: u32) -> u32 {
+    let first = first_of_month(year, month);
+    (first + Months::new(1)).signed_duration_since(first).num_days() as u32
+}
+
+// number of days the 1st is preceded by in its week
+fn lead_days(year: i32, month: u32, first_weekday: Weekday) -> u32 {
+    let first = first_of_month(year, month).weekday();
+    (first.num_days_from_monday() + 7 - first_weekday.num_days_from_monday()) % 7
+}
+
+// the first day of the week holding the 1st, i.e. the date shown in the top-left cell
+fn first_visible(year: i32, month: u32, first_weekday: Weekday) -> NaiveDate {
+    first_of_month(year, month) - Days::new(lead_days(year, month, first_weekday) as u64)
+}
+
+// whether a date is within the years the calendar shows
+fn in_years(date: NaiveDate) -> bool {
+    (FIRST_YEAR..=LAST_YEAR).contains(&date.year())
+}
+
+// number of week rows needed to show the whole month (4 to 6)
+fn rows_needed(year: i32, month: u32, first_weekday: Weekday) -> i32 {
+    ((lead_days(year, month, first_weekday) + days_in_month(year, month) + 6) / 7) as i32
+}
+
+// the date shown in a given cell, which may fall in the previous or next month
+fn cell_date(year: i32, month: u32, first_weekday: Weekday, row: i32, col: i32) -> NaiveDate {
+    first_visible(year, month, first_weekday) + Days::new((row * 7 + col) as u64)
+}
+
+// resize the table rows and columns to fit the weeks of the shown month,
+// leaving room for the table's frame so no scrollbars show up
+fn layout_rows(table: &mut table::TableRow, state: &State) {
+    let rows = rows_needed(state.year, state.month, state.locale.first_weekday());
+    let cols = 7 + state.week_cols();
+    table.set_rows(rows);
+    table.set_cols(cols);
+    table.set_col_width_all(((table.w() - 4) / cols).max(1));
+    table.set_row_height_all(((table.h() - table.col_header_height() - 4) / rows).max(1));
+}
+
 // draw header with day names
 fn draw_header(txt: &str, x: i32, y: i32, w: i32, h: i32) {
     draw::push_clip(x, y, w, h);
@@ -161,16 +596,50 @@ fn draw_header(txt: &str, x: i32, y: i32, w: i32, h: i32) {
     draw::pop_clip();
 }
 
-// draw the numbers
-fn draw_data(day: i32, x: i32, y: i32, w: i32, h: i32, selected: bool) {
+// how a day cell should be drawn
+struct CellStyle {
+    selected: bool,
+    in_range: bool,
+    faded: bool,
+    disabled: bool,
+    // the number of appointments on that day
+    events: usize,
+}
+
+// draw the numbers, days of the adjacent months and disabled days are faded,
+// days with appointments get a dot per appointment, or their count when there are many
+fn draw_data(day: u32, x: i32, y: i32, w: i32, h: i32, style: &CellStyle) {
     draw::push_clip(x, y, w, h);
-    if selected {
+    if style.selected {
         draw::set_draw_color(Color::from_u32(0xbcd9ea));
+    } else if style.in_range {
+        draw::set_draw_color(Color::from_u32(0xdfecf4));
+    } else if style.disabled {
+        draw::set_draw_color(Color::from_u32(0xeeeeee));
     } else {
         draw::set_draw_color(Color::White);
     }
     draw::draw_rectf(x, y, w, h);
-    draw::set_draw_color(Color::Gray0);
+    if style.faded || style.disabled {
+        draw::set_draw_color(Color::from_u32(0xa0a0a0));
+    } else {
+        draw::set_draw_color(Color::Gray0);
+    }
     draw::draw_text2(&format!("{}", day), x, y, w, h, Align::Center);
+    if style.events > 0 {
+        draw::set_draw_color(Color::from_u32(0x3a7bd5));
+        if style.events <= 3 {
+            let dots = style.events as i32;
+            let mut dot_x = x + (w - dots * 8 + 3) / 2;
+            for _ in 0..dots {
+                draw::draw_pie(dot_x, y + h - 9, 5, 5, 0., 360.);
+                dot_x += 8;
+            }
+        } else {
+            draw::set_font(Font::Helvetica, 10);
+            draw::draw_text2(&format!("{}", style.events), x, y + h - 13, w, 12, Align::Center);
+            draw::set_font(Font::Helvetica, 14);
+        }
+    }
     draw::pop_clip();
 }
@@ -0,0 +1,564 @@
+use chrono::Datelike;
+use chrono::Days;
+use chrono::Local;
+use chrono::Months;
+use chrono::NaiveDate;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+// the number of months whose recurring events are kept expanded
+const EXPANDED_MONTHS: usize = 12;
+// the longest span a single occurrence is allowed to cover
+const MAX_SPAN_DAYS: i64 = 366;
+
+/// Defines an appointment on a given day
+#[derive(Debug, Clone, PartialEq)]
+pub struct Appointment {
+    /// The title of the appointment
+    pub summary: String,
+    /// The start time, None for all-day appointments
+    pub time: Option<NaiveTime>,
+}
+
+impl std::fmt::Display for Appointment {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.time {
+            Some(time) => write!(f, "{} {}", time.format("%H:%M"), self.summary),
+            None => write!(f, "{}", self.summary),
+        }
+    }
+}
+
+/// Defines a store of appointments keyed by day
+#[derive(Debug, Clone, Default)]
+pub struct EventStore {
+    days: BTreeMap<NaiveDate, Vec<Appointment>>,
+    // the imported events, which are expanded a month at a time when it's shown
+    events: Vec<Event>,
+    expanded: RefCell<HashMap<(i32, u32), MonthDays>>,
+}
+
+// the appointments of the days of a month, the 1st first
+type MonthDays = Vec<Vec<Appointment>>;
+
+impl EventStore {
+    /// Creates an empty event store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Loads the events of an iCalendar (.ics) file
+    pub fn load_ics<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::from_ics(&std::fs::read_to_string(path)?))
+    }
+    /// Parses the VEVENTs of an iCalendar text, events which can't be parsed are skipped.
+    /// DTSTART, DTEND, SUMMARY and RRULEs with a DAILY, WEEKLY or MONTHLY frequency are
+    /// supported. Times with a TZID are converted to the local time, unknown zones being
+    /// taken as local
+    pub fn from_ics(text: &str) -> Self {
+        let mut store = Self::new();
+        let mut event: Option<VEvent> = None;
+        for line in unfold(text) {
+            let Some((name, params, value)) = split_property(&line) else {
+                continue;
+            };
+            match (name.as_str(), event.as_mut()) {
+                ("BEGIN", _) if value == "VEVENT" => event = Some(VEvent::default()),
+                ("END", Some(_)) if value == "VEVENT" => {
+                    if let Some(ev) = event.take().and_then(VEvent::finish) {
+                        store.events.push(ev);
+                    }
+                }
+                ("DTSTART", Some(ev)) => {
+                    ev.start = parse_datetime(&value, &params);
+                    ev.all_day = is_date(&value, &params);
+                }
+                ("DTEND", Some(ev)) => ev.end = parse_datetime(&value, &params),
+                ("SUMMARY", Some(ev)) => ev.summary = unescape(&value),
+                ("RRULE", Some(ev)) => ev.rule = Rule::parse(&value),
+                _ => (),
+            }
+        }
+        store
+    }
+    /// Adds an appointment to a given day
+    pub fn add(&mut self, date: NaiveDate, appointment: Appointment) {
+        let day = self.days.entry(date).or_default();
+        // all-day appointments first, then by time, keeping the order they were added in
+        let at = day.partition_point(|a| a.time <= appointment.time);
+        day.insert(at, appointment);
+    }
+    /// Gets the appointments of a given day, all-day ones first, then by time
+    pub fn on(&self, date: NaiveDate) -> Vec<Appointment> {
+        let mut appointments = self.days.get(&date).cloned().unwrap_or_default();
+        if self.events.is_empty() {
+            return appointments;
+        }
+        let mut expanded = self.expanded.borrow_mut();
+        let month = (date.year(), date.month());
+        if !expanded.contains_key(&month) {
+            if expanded.len() >= EXPANDED_MONTHS {
+                expanded.clear();
+            }
+            expanded.insert(month, self.expand(month));
+        }
+        if let Some(day) = expanded[&month].get(date.day0() as usize) {
+            appointments.extend(day.iter().cloned());
+            appointments.sort_by_key(|a| a.time);
+        }
+        appointments
+    }
+    // the appointments of the imported events on each day of a month
+    fn expand(&self, (year, month): (i32, u32)) -> MonthDays {
+        let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+            return vec![];
+        };
+        let Some(last) = first.checked_add_months(Months::new(1)).and_then(|d| d.pred_opt()) else {
+            return vec![];
+        };
+        let mut days: MonthDays = vec![vec![]; last.day() as usize];
+        for ev in &self.events {
+            // occurrences starting before the month may still cover its first days
+            let from = before(first, ev.span);
+            let starts = match &ev.rule {
+                Some(rule) => rule.occurrences(ev.start, from, last),
+                None if (from..=last).contains(&ev.start) => vec![ev.start],
+                None => vec![],
+            };
+            for (i, day) in days.iter_mut().enumerate() {
+                let date = first + Days::new(i as u64);
+                // an occurrence starting within the span before the day covers it
+                let earliest = before(date, ev.span);
+                let at = starts.partition_point(|d| *d < earliest);
+                if starts.get(at).is_some_and(|d| *d <= date) {
+                    day.push(Appointment {
+                        summary: ev.summary.clone(),
+                        time: ev.time,
+                    });
+                }
+            }
+        }
+        days
+    }
+}
+
+// an imported event, which may recur
+#[derive(Debug, Clone)]
+struct Event {
+    summary: String,
+    time: Option<NaiveTime>,
+    start: NaiveDate,
+    // the number of days each occurrence covers
+    span: u64,
+    rule: Option<Rule>,
+}
+
+// a VEVENT being parsed
+#[derive(Default)]
+struct VEvent {
+    summary: String,
+    start: Option<NaiveDateTime>,
+    end: Option<NaiveDateTime>,
+    // whether DTSTART is a DATE rather than a DATE-TIME
+    all_day: bool,
+    rule: Option<Rule>,
+}
+
+impl VEvent {
+    // the event once parsed, None without a start
+    fn finish(self) -> Option<Event> {
+        let start = self.start?;
+        // number of days each occurrence covers, DTEND being exclusive
+        let span = match self.end {
+            Some(end) if end > start => {
+                let days = end.date().signed_duration_since(start.date()).num_days();
+                if is_midnight(end) {
+                    days
+                } else {
+                    days + 1
+                }
+            }
+            _ => 1,
+        }
+        .clamp(1, MAX_SPAN_DAYS) as u64;
+        Some(Event {
+            summary: self.summary,
+            time: (!self.all_day).then(|| start.time()),
+            start: start.date(),
+            span,
+            rule: self.rule,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// a simple RRULE, only the frequency, interval, count and end date are considered
+#[derive(Debug, Clone)]
+struct Rule {
+    freq: Freq,
+    interval: u32,
+    count: Option<u32>,
+    until: Option<NaiveDate>,
+}
+
+impl Rule {
+    fn parse(value: &str) -> Option<Self> {
+        let mut freq = None;
+        let mut interval = 1;
+        let mut count = None;
+        let mut until = None;
+        for part in value.split(';') {
+            let Some((key, val)) = part.split_once('=') else {
+                continue;
+            };
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = match val.to_ascii_uppercase().as_str() {
+                        "DAILY" => Some(Freq::Daily),
+                        "WEEKLY" => Some(Freq::Weekly),
+                        "MONTHLY" => Some(Freq::Monthly),
+                        _ => None,
+                    }
+                }
+                "INTERVAL" => interval = val.parse().unwrap_or(1).max(1),
+                "COUNT" => count = val.parse().ok(),
+                "UNTIL" => until = parse_datetime(val, &[]).map(|dt| dt.date()),
+                _ => (),
+            }
+        }
+        Some(Self {
+            freq: freq?,
+            interval,
+            count,
+            until,
+        })
+    }
+
+    // the dates between from and to, both included, which the rule recurs on when it
+    // starts on the given date. earlier occurrences are skipped without being listed
+    fn occurrences(&self, start: NaiveDate, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let to = self.until.map_or(to, |until| until.min(to));
+        let count = self.count.unwrap_or(u32::MAX) as u64;
+        let interval = self.interval as u64;
+        let mut dates = vec![];
+        if to < start || to < from {
+            return dates;
+        }
+        match self.freq {
+            Freq::Daily | Freq::Weekly => {
+                let step = if self.freq == Freq::Daily { interval } else { interval * 7 };
+                // the first occurrence on or after from
+                let ahead = from.signed_duration_since(start).num_days().max(0) as u64;
+                let mut k = ahead.div_ceil(step);
+                while k < count {
+                    let Some(date) = start.checked_add_days(Days::new(k * step)) else {
+                        break;
+                    };
+                    if date > to {
+                        break;
+                    }
+                    dates.push(date);
+                    k += 1;
+                }
+            }
+            Freq::Monthly => {
+                let months = |d: NaiveDate| d.year() as i64 * 12 + d.month0() as i64;
+                let ahead = (months(from) - months(start)).max(0) as u64 / interval;
+                // months lacking the start day, e.g. the 31st, are skipped and not counted
+                let month_after = |k: u64| {
+                    u32::try_from(k * interval)
+                        .ok()
+                        .and_then(|n| start.checked_add_months(Months::new(n)))
+                };
+                let has_day = |k: u64| month_after(k).is_some_and(|d| d.day() == start.day());
+                let mut counted = match self.count {
+                    Some(_) if start.day() > 28 => {
+                        (0..ahead).filter(|&k| has_day(k)).count() as u64
+                    }
+                    _ => ahead,
+                };
+                let mut k = ahead;
+                while counted < count {
+                    let Some(date) = month_after(k) else {
+                        break;
+                    };
+                    if date > to {
+                        break;
+                    }
+                    if date.day() == start.day() {
+                        counted += 1;
+                        if date >= from {
+                            dates.push(date);
+                        }
+                    }
+                    k += 1;
+                }
+            }
+        }
+        dates
+    }
+}
+
+// the first day of a span of days ending on a date
+fn before(date: NaiveDate, span: u64) -> NaiveDate {
+    date.checked_sub_days(Days::new(span - 1)).unwrap_or(NaiveDate::MIN)
+}
+
+fn is_midnight(dt: NaiveDateTime) -> bool {
+    dt.time() == NaiveTime::MIN
+}
+
+// join the folded lines, continuation lines start with a space or a tab
+fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for line in text.lines() {
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+// split a content line into its uppercased name, its parameters and its value.
+// parameter names are uppercased, their values unquoted, and colons and semicolons
+// within quotes don't split the line
+fn split_property(line: &str) -> Option<(String, Vec<(String, String)>, String)> {
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars();
+    let value = loop {
+        match chars.next()? {
+            '"' => quoted = !quoted,
+            ';' if !quoted => parts.push(std::mem::take(&mut part)),
+            ':' if !quoted => {
+                parts.push(part);
+                break chars.as_str();
+            }
+            c => part.push(c),
+        }
+    };
+    let mut parts = parts.into_iter();
+    let name = parts.next()?.trim().to_ascii_uppercase();
+    let params = parts
+        .filter_map(|p| {
+            let (key, val) = p.split_once('=')?;
+            Some((key.trim().to_ascii_uppercase(), val.to_string()))
+        })
+        .collect();
+    Some((name, params, value.trim().to_string()))
+}
+
+// the value of a parameter
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params.iter().find(|(key, _)| key == name).map(|(_, val)| val.as_str())
+}
+
+// whether a value is a DATE rather than a DATE-TIME
+fn is_date(value: &str, params: &[(String, String)]) -> bool {
+    param(params, "VALUE").is_some_and(|v| v.eq_ignore_ascii_case("DATE")) || !value.contains('T')
+}
+
+// parse a DATE or a DATE-TIME value. UTC times and times in a known TZID are converted to
+// the local time, other times are taken as local
+fn parse_datetime(value: &str, params: &[(String, String)]) -> Option<NaiveDateTime> {
+    if is_date(value, params) {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        return Some(date.and_time(NaiveTime::MIN));
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let dt = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Local.from_utc_datetime(&dt).naive_local());
+    }
+    let dt = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    let zoned = param(params, "TZID")
+        .and_then(|tzid| tzid.parse::<Tz>().ok())
+        .and_then(|tz| tz.from_local_datetime(&dt).earliest())
+        .map(|dt| dt.with_timezone(&Local).naive_local());
+    Some(zoned.unwrap_or(dt))
+}
+
+// undo the TEXT escaping of commas, semicolons, backslashes and newlines
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n') | Some('N') => out.push(' '),
+                Some(c) => out.push(c),
+                None => (),
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn time(h: u32, m: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(h, m, 0)
+    }
+
+    fn summaries(store: &EventStore, date: NaiveDate) -> Vec<String> {
+        store.on(date).into_iter().map(|a| a.summary).collect()
+    }
+
+    #[test]
+    fn unfold_lines() {
+        let lines = unfold("SUMMARY:A long\r\n  summary\r\n\tcontinued\r\nDTSTART:20240101\r\n");
+        assert_eq!(lines, ["SUMMARY:A long summarycontinued", "DTSTART:20240101"]);
+    }
+
+    #[test]
+    fn quoted_parameters() {
+        let (name, params, value) =
+            split_property("dtstart;TZID=\"Custom: zone\";VALUE=DATE-TIME:20240101T090000")
+                .unwrap();
+        assert_eq!(name, "DTSTART");
+        assert_eq!(param(&params, "TZID"), Some("Custom: zone"));
+        assert_eq!(param(&params, "VALUE"), Some("DATE-TIME"));
+        assert_eq!(value, "20240101T090000");
+    }
+
+    #[test]
+    fn from_ics() {
+        let store = EventStore::from_ics(
+            "BEGIN:VCALENDAR
+BEGIN:VEVENT
+SUMMARY:Stand\\, up
+DTSTART:20240105T093000
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:Trip
+DTSTART;VALUE=DATE:20240110
+DTEND;VALUE=DATE:20240113
+END:VEVENT
+BEGIN:VEVENT
+SUMMARY:Unknown zone
+DTSTART;TZID=\"Nowhere: zone\":20240105T080000
+END:VEVENT
+BEGIN:VEVENT
+DTSTART:20240106T080000
+END:VEVENT
+END:VCALENDAR
+",
+        );
+        let day = store.on(date(2024, 1, 5));
+        assert_eq!(day.len(), 2);
+        assert_eq!(day[0].summary, "Unknown zone");
+        assert_eq!(day[0].time, time(8, 0));
+        assert_eq!(day[1].summary, "Stand, up");
+        assert_eq!(day[1].time, time(9, 30));
+        // DTEND is exclusive
+        for d in 10..13 {
+            let day = store.on(date(2024, 1, d));
+            assert_eq!(summaries(&store, date(2024, 1, d)), ["Trip"]);
+            assert_eq!(day[0].time, None);
+        }
+        assert!(store.on(date(2024, 1, 13)).is_empty());
+        assert_eq!(summaries(&store, date(2024, 1, 6)), [""]);
+    }
+
+    #[test]
+    fn time_zones() {
+        let store = EventStore::from_ics(
+            "BEGIN:VEVENT
+SUMMARY:Call
+DTSTART;TZID=Etc/UTC:20240105T120000
+END:VEVENT
+",
+        );
+        let utc = date(2024, 1, 5).and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap());
+        let local = Local.from_utc_datetime(&utc).naive_local();
+        assert_eq!(store.on(local.date())[0].time, Some(local.time()));
+    }
+
+    #[test]
+    fn long_recurrences() {
+        // a daily event covering most of a year since 1900 is only expanded where shown
+        let store = EventStore::from_ics(
+            "BEGIN:VEVENT
+SUMMARY:Always
+DTSTART;VALUE=DATE:19000101
+DTEND;VALUE=DATE:19001231
+RRULE:FREQ=DAILY
+END:VEVENT
+",
+        );
+        assert_eq!(summaries(&store, date(2024, 6, 15)), ["Always"]);
+        assert_eq!(summaries(&store, date(1900, 1, 1)), ["Always"]);
+        assert!(store.on(date(1899, 12, 31)).is_empty());
+    }
+
+    #[test]
+    fn monthly_skips_short_months() {
+        let rule = Rule::parse("FREQ=MONTHLY").unwrap();
+        let dates = rule.occurrences(date(2024, 1, 31), date(2024, 1, 1), date(2024, 12, 31));
+        let months: Vec<u32> = dates.iter().map(|d| d.month()).collect();
+        assert_eq!(months, [1, 3, 5, 7, 8, 10, 12]);
+        assert!(dates.iter().all(|d| d.day() == 31));
+    }
+
+    #[test]
+    fn count() {
+        let rule = Rule::parse("FREQ=MONTHLY;COUNT=3").unwrap();
+        let start = date(2024, 1, 31);
+        let all = rule.occurrences(start, start, date(2025, 12, 31));
+        assert_eq!(all, [start, date(2024, 3, 31), date(2024, 5, 31)]);
+        // the occurrences before from still count
+        let later = rule.occurrences(start, date(2024, 4, 1), date(2025, 12, 31));
+        assert_eq!(later, [date(2024, 5, 31)]);
+
+        let rule = Rule::parse("FREQ=DAILY;INTERVAL=2;COUNT=3").unwrap();
+        let start = date(2024, 1, 1);
+        let all = rule.occurrences(start, start, date(2024, 12, 31));
+        assert_eq!(all, [start, date(2024, 1, 3), date(2024, 1, 5)]);
+        let later = rule.occurrences(start, date(2024, 1, 4), date(2024, 12, 31));
+        assert_eq!(later, [date(2024, 1, 5)]);
+    }
+
+    #[test]
+    fn until() {
+        let rule = Rule::parse("FREQ=WEEKLY;UNTIL=20240122").unwrap();
+        let start = date(2024, 1, 1);
+        let dates = rule.occurrences(start, start, date(2024, 12, 31));
+        assert_eq!(dates, [start, date(2024, 1, 8), date(2024, 1, 15), date(2024, 1, 22)]);
+    }
+
+    #[test]
+    fn add_keeps_order() {
+        let mut store = EventStore::new();
+        let day = date(2024, 1, 1);
+        let added = [("a", time(10, 0)), ("b", None), ("c", time(9, 0)), ("d", time(10, 0))];
+        for (summary, at) in added {
+            store.add(
+                day,
+                Appointment {
+                    summary: summary.to_string(),
+                    time: at,
+                },
+            );
+        }
+        assert_eq!(summaries(&store, day), ["b", "c", "a", "d"]);
+    }
+}
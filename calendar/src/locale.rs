@@ -0,0 +1,126 @@
+use chrono::Weekday;
+
+/// Defines how the calendar names and lays out the days of a month
+#[derive(Debug, Clone)]
+pub struct CalendarLocale {
+    first_weekday: Weekday,
+    months: [String; 12],
+    weekdays: [String; 7],
+    week_label: String,
+    week_numbers: bool,
+}
+
+impl Default for CalendarLocale {
+    fn default() -> Self {
+        CalendarLocale::en_gb()
+    }
+}
+
+impl CalendarLocale {
+    /// Creates a custom locale from month names starting with January,
+    /// and weekday names starting with Monday
+    pub fn new(first_weekday: Weekday, months: [&str; 12], weekdays: [&str; 7]) -> Self {
+        Self {
+            first_weekday,
+            months: months.map(String::from),
+            weekdays: weekdays.map(String::from),
+            week_label: "Wk".to_string(),
+            week_numbers: false,
+        }
+    }
+    /// Show a column with the ISO week numbers
+    pub fn with_week_numbers(mut self, flag: bool) -> Self {
+        self.week_numbers = flag;
+        self
+    }
+    /// Set the header of the week number column
+    pub fn with_week_label(mut self, label: &str) -> Self {
+        self.week_label = label.to_string();
+        self
+    }
+    /// Set the day the weeks start on
+    pub fn with_first_weekday(mut self, day: Weekday) -> Self {
+        self.first_weekday = day;
+        self
+    }
+    /// English, weeks start on Sunday
+    pub fn en_us() -> Self {
+        Self::new(
+            Weekday::Sun,
+            [
+                "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+            ],
+            ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+        )
+    }
+    /// English, weeks start on Monday
+    pub fn en_gb() -> Self {
+        Self::en_us().with_first_weekday(Weekday::Mon)
+    }
+    /// German, weeks start on Monday and are numbered
+    pub fn de_de() -> Self {
+        Self::new(
+            Weekday::Mon,
+            [
+                "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+            ],
+            ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+        )
+        .with_week_numbers(true)
+        .with_week_label("KW")
+    }
+    /// French, weeks start on Monday
+    pub fn fr_fr() -> Self {
+        Self::new(
+            Weekday::Mon,
+            [
+                "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.",
+                "nov.", "déc.",
+            ],
+            ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+        )
+        .with_week_label("Sem.")
+    }
+    /// Spanish, weeks start on Monday
+    pub fn es_es() -> Self {
+        Self::new(
+            Weekday::Mon,
+            [
+                "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+            ],
+            ["lu", "ma", "mi", "ju", "vi", "sá", "do"],
+        )
+        .with_week_label("Sem")
+    }
+    /// Brazilian Portuguese, weeks start on Sunday
+    pub fn pt_br() -> Self {
+        Self::new(
+            Weekday::Sun,
+            [
+                "jan", "fev", "mar", "abr", "mai", "jun", "jul", "ago", "set", "out", "nov", "dez",
+            ],
+            ["seg", "ter", "qua", "qui", "sex", "sáb", "dom"],
+        )
+        .with_week_label("Sem")
+    }
+    /// The day the weeks start on
+    pub fn first_weekday(&self) -> Weekday {
+        self.first_weekday
+    }
+    /// The name of a month, 1 being January
+    pub fn month_name(&self, month: u32) -> &str {
+        &self.months[month as usize - 1]
+    }
+    /// The name of a weekday
+    pub fn weekday_name(&self, day: Weekday) -> &str {
+        &self.weekdays[day.num_days_from_monday() as usize]
+    }
+    /// The header of the week number column
+    pub fn week_label(&self) -> &str {
+        &self.week_label
+    }
+    /// Whether a column with the ISO week numbers is shown
+    pub fn week_numbers(&self) -> bool {
+        self.week_numbers
+    }
+}
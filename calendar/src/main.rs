@@ -1,19 +1,75 @@
 mod calendar;
+mod events;
+mod locale;
 
 use fltk::{
     prelude::*,
     *,
 };
 use chrono::prelude::*;
+use chrono::Months;
+use events::EventStore;
+use locale::CalendarLocale;
 
 fn main() {
     let app = app::App::default().with_scheme(app::Scheme::Gtk);
     app::background(0xd3, 0xd3, 0xd3);
-    let mut win = window::Window::new(100, 100, 400, 300, "");
+    let mut win = window::Window::new(100, 100, 400, 400, "");
     win.make_resizable(true);
-    let mut but = button::Button::new(160, 200, 80, 40, "Click");
+    let mut col = group::Flex::default_fill().column();
+    col.set_margin(10);
+    // an embedded calendar, which doesn't block the event loop
+    let mut cal = calendar::CalendarWidget::default();
+    let mut frame = frame::Frame::default();
+    let mut row = group::Flex::default().row();
+    let mut locale_choice = menu::Choice::default();
+    locale_choice.add_choice("en_US|en_GB|de_DE|fr_FR|es_ES|pt_BR");
+    locale_choice.set_value(1);
+    let mut but = button::Button::default().with_label("Click");
+    let mut range_but = button::Button::default().with_label("Range");
+    let mut open_but = button::Button::default().with_label("@fileopen");
+    open_but.set_tooltip("Import an iCalendar (.ics) file");
+    frame::Frame::default();
+    row.end();
+    row.fixed(&but, 80);
+    row.fixed(&range_but, 80);
+    row.fixed(&locale_choice, 80);
+    row.fixed(&open_but, 40);
+    col.fixed(&frame, 30);
+    col.fixed(&row, 40);
+    col.end();
     win.end();
     win.show();
+    locale_choice.set_callback({
+        let mut cal = cal.clone();
+        move |c| {
+            cal.set_locale(locale_from_choice(c.value()));
+        }
+    });
+    open_but.set_callback({
+        let mut cal = cal.clone();
+        move |_| {
+            let mut dlg = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+            dlg.set_filter("*.ics");
+            dlg.show();
+            let path = dlg.filename();
+            if path.as_os_str().is_empty() {
+                return;
+            }
+            match EventStore::load_ics(&path) {
+                Ok(events) => cal.set_events(events),
+                Err(e) => dialog::alert_default(&format!("Couldn't open {:?}: {}", path, e)),
+            }
+        }
+    });
+    cal.set_callback({
+        let mut frame = frame.clone();
+        move |c| {
+            if let Some(date) = c.date() {
+                frame.set_label(&date.format("%A %-d %B %Y").to_string());
+            }
+        }
+    });
     but.set_callback(move |_| {
         let cal = calendar::Calendar::default(); // or calendar::Calendar::new(200, 100);
         let date = cal.get_date();
@@ -24,5 +80,33 @@ fn main() {
             println!("{:?}", date.day());
         }
     });
+    range_but.set_callback(move |_| {
+        // pick a range of weekdays within the next 3 months
+        let today = Local::now().date_naive();
+        let mut cal = calendar::Calendar::dialog(200, 100);
+        cal.set_range_mode(true);
+        cal.set_min_date(today);
+        cal.set_max_date(today + Months::new(3));
+        cal.set_disabled(|d| matches!(d.weekday(), Weekday::Sat | Weekday::Sun));
+        cal.popup();
+        if let Some((start, end)) = cal.get_range() {
+            frame.set_label(&format!(
+                "{} to {}",
+                start.format("%-d %B %Y"),
+                end.format("%-d %B %Y")
+            ));
+        }
+    });
     app.run().unwrap();
 }
+
+fn locale_from_choice(idx: i32) -> CalendarLocale {
+    match idx {
+        0 => CalendarLocale::en_us(),
+        2 => CalendarLocale::de_de(),
+        3 => CalendarLocale::fr_fr(),
+        4 => CalendarLocale::es_es(),
+        5 => CalendarLocale::pt_br(),
+        _ => CalendarLocale::en_gb(),
+    }
+}
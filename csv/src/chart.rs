@@ -0,0 +1,1010 @@
+use crate::compare::Comparison;
+use crate::indicators::{self, Indicators};
+use crate::loader::Price;
+use crate::PRICES;
+use chrono::Datelike;
+use chrono::NaiveDateTime;
+use chrono::NaiveTime;
+use chrono::Timelike;
+use fltk::{app, draw, enums::*, frame, prelude::*};
+use std::ops::Range;
+use std::{cell::RefCell, rc::Rc};
+
+/// The width of the price axis on the right of the plot
+pub const PRICE_AXIS_W: i32 = 60;
+/// The height of the date axis under the plot
+pub const DATE_AXIS_H: i32 = 20;
+// the minimum distance in pixels between two date labels
+const MIN_LABEL_SPACING: f64 = 70.;
+// the minimum distance in pixels between two price ticks
+const MIN_TICK_SPACING: f64 = 40.;
+// the space between the panes
+const PANE_GAP: i32 = 6;
+// the room for the title above an exported chart and for the date axis name below it
+const TITLE_H: i32 = 30;
+const AXIS_NAME_H: i32 = 20;
+
+// the fewest candles shown when zooming in
+const MIN_VISIBLE: f64 = 10.;
+// how much a step of the mouse wheel zooms
+const ZOOM_FACTOR: f64 = 1.2;
+
+const GRID_COLOR: u32 = 0x303030;
+const AXIS_COLOR: u32 = 0x969696;
+const CROSSHAIR_COLOR: u32 = 0x707070;
+const TOOLTIP_COLOR: u32 = 0x202020;
+const SMA_COLOR: u32 = 0xe0c040;
+const EMA_COLOR: u32 = 0x40c0e0;
+const BOLLINGER_COLOR: u32 = 0xa070e0;
+const RSI_COLOR: u32 = 0xe09040;
+const VOLUME_UP_COLOR: u32 = 0x2e6b2e;
+const VOLUME_DOWN_COLOR: u32 = 0x7a2e2e;
+// the colors of the compared series, reused when there are more series
+const SERIES_COLORS: [u32; 8] = [
+    0x4e9fe0, 0xe0a03c, 0x5cc05c, 0xe05c5c, 0xb07ce0, 0x4ec8c0, 0xe07cc0, 0xc8c84e,
+];
+
+/// The range of candles shown, in fractional candle indices
+#[derive(Debug, Clone, Copy)]
+pub struct Viewport {
+    pub start: f64,
+    pub len: f64,
+}
+
+impl Viewport {
+    /// Shows all of the candles
+    pub fn all(count: usize) -> Self {
+        Self {
+            start: 0.,
+            len: count.max(1) as f64,
+        }
+    }
+
+    /// The indices of the candles which are at least partly visible
+    pub fn range(&self, count: usize) -> Range<usize> {
+        let start = (self.start.floor().max(0.) as usize).min(count);
+        let end = ((self.start + self.len).ceil().max(0.) as usize).clamp(start, count);
+        start..end
+    }
+
+    // keep the viewport within the data, showing at least a few candles
+    fn clamp(&mut self, count: usize) {
+        let count = count.max(1) as f64;
+        self.len = self.len.clamp(MIN_VISIBLE.min(count), count);
+        self.start = self.start.clamp(0., count - self.len);
+    }
+
+    // zoom by a factor, keeping the fractional index `anchor` at the same place
+    fn zoom(&mut self, factor: f64, anchor: f64, count: usize) {
+        let old_len = self.len;
+        self.len *= factor;
+        self.clamp(count);
+        self.start = anchor - (anchor - self.start) * self.len / old_len;
+        self.clamp(count);
+    }
+}
+
+/// A pane under the candles, sharing their horizontal layout
+#[derive(Debug, Clone, Copy)]
+pub struct Pane {
+    pub y: i32,
+    pub h: i32,
+}
+
+impl Pane {
+    /// The vertical position of a value, `max` being at the top and 0 at the bottom
+    pub fn value_y(&self, value: f64, max: f64) -> i32 {
+        self.y + ((1. - value / max) * self.h as f64) as i32
+    }
+}
+
+/// Maps prices and candle indices onto the plot area of the chart,
+/// the x, y, w and h being those of the candles' pane
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+    pub low: f64,
+    pub high: f64,
+    pub view: Viewport,
+    pub volume: Option<Pane>,
+    pub rsi: Option<Pane>,
+}
+
+impl Layout {
+    /// Lays out a chart in the given area, leaving room for the axes and the indicator panes.
+    /// The price range spans the lowest low to the highest high of the visible candles, with a small margin
+    pub fn new(
+        prices: &[Price],
+        view: Viewport,
+        indicators: &Indicators,
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+    ) -> Self {
+        let visible = &prices[view.range(prices.len())];
+        let low = visible.iter().map(|p| p.low).fold(f64::INFINITY, f64::min);
+        let high = visible
+            .iter()
+            .map(|p| p.high)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let mut layout = Self::spanning(low, high, view, x, y, w, h);
+        layout.add_panes(indicators);
+        layout
+    }
+
+    /// Lays out a chart without panes whose visible values go from low to high, with a small margin
+    pub fn spanning(low: f64, high: f64, view: Viewport, x: i32, y: i32, w: i32, h: i32) -> Self {
+        let (low, high) = if low.is_finite() && high.is_finite() {
+            let margin = ((high - low) * 0.05).max(high.abs() * 0.001).max(1e-9);
+            (low - margin, high + margin)
+        } else {
+            (0., 1.)
+        };
+        Self {
+            x,
+            y,
+            w: (w - PRICE_AXIS_W).max(1),
+            h: (h - DATE_AXIS_H).max(1),
+            low,
+            high,
+            view,
+            volume: None,
+            rsi: None,
+        }
+    }
+
+    // give each pane under the candles a fifth of the height
+    fn add_panes(&mut self, indicators: &Indicators) {
+        let pane_h = self.h / 5;
+        let panes = indicators.volume as i32 + indicators.rsi.is_some() as i32;
+        let price_h = (self.h - panes * (pane_h + PANE_GAP)).max(1);
+        let mut next_y = self.y + price_h + PANE_GAP;
+        let mut pane = |on: bool| {
+            on.then(|| {
+                let pane = Pane {
+                    y: next_y,
+                    h: pane_h,
+                };
+                next_y += pane_h + PANE_GAP;
+                pane
+            })
+        };
+        self.h = price_h;
+        self.volume = pane(indicators.volume);
+        self.rsi = pane(indicators.rsi.is_some());
+    }
+
+    /// The bottom of the lowest pane, where the date axis starts
+    pub fn bottom(&self) -> i32 {
+        [self.volume, self.rsi]
+            .iter()
+            .flatten()
+            .map(|p| p.y + p.h)
+            .fold(self.y + self.h, i32::max)
+    }
+
+    /// The vertical position of a price
+    pub fn price_y(&self, price: f64) -> i32 {
+        self.y + ((self.high - price) / (self.high - self.low) * self.h as f64) as i32
+    }
+
+    /// The price at a vertical position
+    pub fn y_price(&self, y: i32) -> f64 {
+        self.high - (y - self.y) as f64 / self.h as f64 * (self.high - self.low)
+    }
+
+    /// The width allotted to each candle
+    pub fn step(&self) -> f64 {
+        self.w as f64 / self.view.len
+    }
+
+    /// The horizontal center of a candle
+    pub fn candle_x(&self, idx: usize) -> i32 {
+        self.x + ((idx as f64 - self.view.start + 0.5) * self.step()).round() as i32
+    }
+
+    /// The fractional candle index at a horizontal position
+    pub fn x_index(&self, x: i32) -> f64 {
+        self.view.start + (x - self.x) as f64 / self.step()
+    }
+
+    /// Whether a point lies within the plot area, including the panes
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x < self.x + self.w && y >= self.y && y < self.bottom()
+    }
+}
+
+/// Picks round tick values, 1, 2 or 5 times a power of ten apart, within the range
+pub fn nice_ticks(low: f64, high: f64, max_ticks: usize) -> Vec<f64> {
+    let range = high - low;
+    if range <= 0. || max_ticks == 0 {
+        return vec![];
+    }
+    let raw = range / max_ticks as f64;
+    let mag = 10f64.powf(raw.log10().floor());
+    let step = [1., 2., 5., 10.]
+        .iter()
+        .map(|m| m * mag)
+        .find(|s| *s >= raw)
+        .unwrap_or(10. * mag);
+    let mut ticks = vec![];
+    let mut tick = (low / step).ceil() * step;
+    while tick <= high {
+        ticks.push(tick);
+        tick += step;
+    }
+    ticks
+}
+
+// format a price with just enough decimals for the tick step
+fn format_price(price: f64, step: f64) -> String {
+    let decimals = if step >= 1. {
+        0
+    } else {
+        (-step.log10()).ceil() as usize
+    };
+    format!("{:.*}", decimals, price)
+}
+
+/// Formats the date of a candle, with its time for intraday candles
+pub fn format_date(date: NaiveDateTime) -> String {
+    if date.time() == NaiveTime::MIN {
+        date.date().to_string()
+    } else {
+        date.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+// the visible candles starting a new hour, day, week or month, whichever is the finest to fit, with their labels
+fn date_labels(dates: &[NaiveDateTime], layout: &Layout) -> Vec<(usize, String)> {
+    let range = layout.view.range(dates.len());
+    let boundaries = |key: fn(NaiveDateTime) -> i64| -> Vec<usize> {
+        range
+            .clone()
+            .filter(|&i| i == 0 || key(dates[i]) != key(dates[i - 1]))
+            .collect()
+    };
+    let fits = |idxs: &[usize]| layout.w as f64 / idxs.len().max(1) as f64 >= MIN_LABEL_SPACING;
+    // hours only make sense for intraday candles
+    if range.clone().any(|i| dates[i].time() != NaiveTime::MIN) {
+        let hours = boundaries(|d| d.date().num_days_from_ce() as i64 * 24 + d.hour() as i64);
+        if fits(&hours) {
+            return hours
+                .into_iter()
+                .map(|i| (i, dates[i].format("%H:%M").to_string()))
+                .collect();
+        }
+    }
+    let days = boundaries(|d| d.num_days_from_ce() as i64);
+    if fits(&days) {
+        return days
+            .into_iter()
+            .map(|i| (i, dates[i].format("%d %b").to_string()))
+            .collect();
+    }
+    let weeks = boundaries(|d| {
+        let week = d.iso_week();
+        week.year() as i64 * 100 + week.week() as i64
+    });
+    if fits(&weeks) {
+        return weeks
+            .into_iter()
+            .map(|i| (i, dates[i].format("%d %b").to_string()))
+            .collect();
+    }
+    // months, keeping every few of them when even those don't fit
+    let month = |d: NaiveDateTime| d.year() as i64 * 12 + d.month0() as i64;
+    let months = boundaries(month);
+    let every = (MIN_LABEL_SPACING * months.len() as f64 / layout.w as f64).ceil() as i64;
+    months
+        .into_iter()
+        .filter(|&i| month(dates[i]) % every.max(1) == 0)
+        .map(|i| (i, dates[i].format("%b %y").to_string()))
+        .collect()
+}
+
+// the dates and the indicator series of the candles, which are only computed again when
+// the data or the indicator settings change
+struct Studies {
+    dates: Vec<NaiveDateTime>,
+    bollinger: Option<Vec<Option<(f64, f64, f64)>>>,
+    sma: Option<Vec<Option<f64>>>,
+    ema: Option<Vec<Option<f64>>>,
+    rsi: Option<Vec<Option<f64>>>,
+}
+
+impl Studies {
+    fn new(prices: &[Price], indicators: &Indicators) -> Self {
+        let closes: Vec<f64> = prices.iter().map(|p| p.close).collect();
+        Self {
+            dates: prices.iter().map(|p| p.date).collect(),
+            bollinger: indicators
+                .bollinger
+                .map(|(period, k)| indicators::bollinger(&closes, period, k)),
+            sma: indicators.sma.map(|period| indicators::sma(&closes, period)),
+            ema: indicators.ema.map(|period| indicators::ema(&closes, period)),
+            rsi: indicators.rsi.map(|period| indicators::rsi(&closes, period)),
+        }
+    }
+}
+
+// the state of the chart which isn't part of the data
+struct ChartState {
+    view: Viewport,
+    // the mouse position while it's over the plot
+    crosshair: Option<(i32, i32)>,
+    // the mouse x and viewport start when a drag started
+    drag: Option<(i32, f64)>,
+    indicators: Indicators,
+    // the symbols compared instead of the candles of the `PRICES`
+    comparison: Option<Comparison>,
+    // None after the `PRICES` or the indicators changed
+    studies: Option<Studies>,
+}
+
+impl ChartState {
+    // the dates along the x axis
+    fn dates(&self, prices: &[Price]) -> Vec<NaiveDateTime> {
+        match &self.comparison {
+            Some(c) => c.dates.clone(),
+            None => prices.iter().map(|p| p.date).collect(),
+        }
+    }
+
+    fn count(&self, prices: &[Price]) -> usize {
+        self.comparison
+            .as_ref()
+            .map_or(prices.len(), |c| c.dates.len())
+    }
+
+    // lay out the candles with their indicator panes, or the compared series
+    fn layout(&self, prices: &[Price], x: i32, y: i32, w: i32, h: i32) -> Layout {
+        match &self.comparison {
+            Some(c) => {
+                let range = self.view.range(c.dates.len());
+                let (low, high) = c
+                    .normalized(range.clone())
+                    .iter()
+                    .flat_map(|values| values[range.clone()].iter().flatten())
+                    .fold((0., 0.), |(low, high), v| (v.min(low), v.max(high)));
+                Layout::spanning(low, high, self.view, x, y, w, h)
+            }
+            None => Layout::new(prices, self.view, &self.indicators, x, y, w, h),
+        }
+    }
+
+    // the layout within a frame and the number of candles or dates
+    fn frame_layout(&self, f: &frame::Frame) -> (Layout, usize) {
+        let data = PRICES.lock().unwrap();
+        let (x, y, w, h) = plot_area(f);
+        (self.layout(&data, x, y, w, h), self.count(&data))
+    }
+
+    fn draw(&mut self, prices: &[Price], layout: &Layout) {
+        match &self.comparison {
+            Some(c) => draw_comparison(c, layout),
+            None => {
+                let studies = self
+                    .studies
+                    .get_or_insert_with(|| Studies::new(prices, &self.indicators));
+                draw_chart(prices, layout, studies);
+            }
+        }
+    }
+
+    fn draw_crosshair(&self, prices: &[Price], layout: &Layout, mx: i32, my: i32) {
+        match &self.comparison {
+            Some(c) => draw_comparison_crosshair(c, layout, mx, my),
+            None => draw_crosshair(prices, layout, mx, my),
+        }
+    }
+}
+
+/// Defines a candlestick chart of the `PRICES`, which can be zoomed with the mouse wheel,
+/// panned by dragging, and inspected by hovering
+#[derive(Clone)]
+pub struct Chart {
+    frame: frame::Frame,
+    state: Rc<RefCell<ChartState>>,
+}
+
+impl Default for Chart {
+    fn default() -> Self {
+        Chart::new(0, 0, 0, 0, None)
+    }
+}
+
+impl Chart {
+    pub fn new<L: Into<Option<&'static str>>>(x: i32, y: i32, w: i32, h: i32, label: L) -> Self {
+        let mut frame = frame::Frame::new(x, y, w, h, label);
+        frame.set_frame(FrameType::DownBox);
+        frame.set_color(Color::Black);
+        let state = Rc::from(RefCell::from(ChartState {
+            view: Viewport::all(0),
+            crosshair: None,
+            drag: None,
+            indicators: Indicators::default(),
+            comparison: None,
+            studies: None,
+        }));
+
+        frame.draw({
+            let state = state.clone();
+            move |f| {
+                let data = PRICES.lock().unwrap();
+                let mut state = state.borrow_mut();
+                let (x, y, w, h) = plot_area(f);
+                let layout = state.layout(&data, x, y, w, h);
+                state.draw(&data, &layout);
+                if let Some((mx, my)) = state.crosshair {
+                    state.draw_crosshair(&data, &layout, mx, my);
+                }
+            }
+        });
+
+        frame.handle({
+            let state = state.clone();
+            move |f, ev| {
+                let mut state = state.borrow_mut();
+                let (mx, my) = app::event_coords();
+                match ev {
+                    Event::Enter | Event::Move => {
+                        let (layout, _) = state.frame_layout(f);
+                        let crosshair = layout.contains(mx, my).then_some((mx, my));
+                        if state.crosshair != crosshair {
+                            state.crosshair = crosshair;
+                            f.redraw();
+                        }
+                        true
+                    }
+                    Event::Leave => {
+                        state.crosshair = None;
+                        f.redraw();
+                        true
+                    }
+                    Event::MouseWheel => {
+                        let factor = match app::event_dy() {
+                            app::MouseWheel::Up => 1. / ZOOM_FACTOR,
+                            app::MouseWheel::Down => ZOOM_FACTOR,
+                            _ => return false,
+                        };
+                        let (layout, count) = state.frame_layout(f);
+                        state.view.zoom(factor, layout.x_index(mx), count);
+                        f.redraw();
+                        true
+                    }
+                    Event::Push => {
+                        state.drag = Some((mx, state.view.start));
+                        true
+                    }
+                    Event::Drag => {
+                        if let Some((x0, start)) = state.drag {
+                            let (layout, count) = state.frame_layout(f);
+                            state.view.start = start - (mx - x0) as f64 / layout.step();
+                            state.view.clamp(count);
+                            state.crosshair = layout.contains(mx, my).then_some((mx, my));
+                            f.redraw();
+                        }
+                        true
+                    }
+                    Event::Released => {
+                        state.drag = None;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        });
+
+        Self { frame, state }
+    }
+
+    /// Sets the studies drawn along with the candles
+    pub fn set_indicators(&mut self, indicators: Indicators) {
+        let mut state = self.state.borrow_mut();
+        state.indicators = indicators;
+        state.studies = None;
+        drop(state);
+        self.frame.redraw();
+    }
+
+    /// Compares several symbols instead of showing the candles of the `PRICES`, or stops comparing
+    pub fn set_comparison(&mut self, comparison: Option<Comparison>) {
+        self.state.borrow_mut().comparison = comparison;
+        self.reset_view();
+    }
+
+    /// Draws the visible candles or series onto the current surface at a given size,
+    /// with a title naming the symbol and the dates, and the names of the axes
+    pub fn render(&self, symbol: &str, w: i32, h: i32) {
+        let data = PRICES.lock().unwrap();
+        let mut state = self.state.borrow_mut();
+        draw::set_draw_color(Color::Black);
+        draw::draw_rectf(0, 0, w, h);
+        let layout = state.layout(&data, 4, TITLE_H, w - 8, h - TITLE_H - AXIS_NAME_H);
+        state.draw(&data, &layout);
+
+        let dates = state.dates(&data);
+        let visible = &dates[state.view.range(dates.len())];
+        let title = match (visible.first(), visible.last()) {
+            (Some(first), Some(last)) => format!(
+                "{}  {} to {}",
+                symbol,
+                format_date(*first),
+                format_date(*last)
+            ),
+            _ => symbol.to_string(),
+        };
+        draw::set_draw_color(Color::White);
+        draw::set_font(Font::HelveticaBold, 14);
+        draw::draw_text2(&title, layout.x, 0, layout.w, TITLE_H, Align::Left);
+        draw::set_draw_color(Color::from_u32(AXIS_COLOR));
+        draw::set_font(Font::Helvetica, 11);
+        let axis_name = if state.comparison.is_some() {
+            "Change"
+        } else {
+            "Price"
+        };
+        draw::draw_text2(
+            axis_name,
+            layout.x + layout.w + 6,
+            0,
+            PRICE_AXIS_W - 6,
+            TITLE_H,
+            Align::Left | Align::Bottom,
+        );
+        draw::draw_text2(
+            "Date",
+            layout.x,
+            layout.bottom() + DATE_AXIS_H,
+            layout.w,
+            AXIS_NAME_H,
+            Align::Center,
+        );
+    }
+
+    /// Redraws after candles were added to the `PRICES`, or the last one was updated.
+    /// When the newest candle was in view, the view follows the new candles
+    pub fn follow(&mut self, old_count: usize) {
+        let count = PRICES.lock().unwrap().len();
+        let mut state = self.state.borrow_mut();
+        state.studies = None;
+        let view = &mut state.view;
+        if view.start + view.len >= old_count as f64 {
+            if view.start <= 0. {
+                // everything was shown, keep showing everything
+                *view = Viewport::all(count);
+            } else {
+                view.start += count.saturating_sub(old_count) as f64;
+                view.clamp(count);
+            }
+        }
+        self.frame.redraw();
+    }
+
+    /// Shows all of the candles, e.g. after loading new data
+    pub fn reset_view(&mut self) {
+        let data = PRICES.lock().unwrap();
+        let mut state = self.state.borrow_mut();
+        let count = state.count(&data);
+        state.view = Viewport::all(count);
+        state.studies = None;
+        self.frame.redraw();
+    }
+}
+
+fltk::widget_extends!(Chart, frame::Frame, frame);
+
+// the area the chart is drawn in, inside the frame's box
+fn plot_area(f: &frame::Frame) -> (i32, i32, i32, i32) {
+    (f.x() + 2, f.y() + 2, f.w() - 4, f.h() - 4)
+}
+
+// draw the visible candlesticks with a price axis, a date axis, gridlines and the indicators
+fn draw_chart(prices: &[Price], layout: &Layout, studies: &Studies) {
+    let bottom = layout.bottom();
+    draw::push_clip(
+        layout.x,
+        layout.y,
+        layout.w + PRICE_AXIS_W,
+        bottom - layout.y + DATE_AXIS_H,
+    );
+    draw_axes(&studies.dates, layout, "");
+
+    // the candles, red when the price closed below its open
+    let range = layout.view.range(prices.len());
+    draw::push_clip(layout.x, layout.y, layout.w, layout.h);
+    let body_w = ((layout.step() * 0.6) as i32).max(1);
+    for idx in range.clone() {
+        let elem = &prices[idx];
+        let cx = layout.candle_x(idx);
+        let open = layout.price_y(elem.open);
+        let high = layout.price_y(elem.high);
+        let low = layout.price_y(elem.low);
+        let close = layout.price_y(elem.close);
+        draw::set_draw_color(Color::White);
+        draw::draw_line(cx, high, cx, low);
+        let col = if close > open {
+            Color::Red
+        } else {
+            Color::Green
+        };
+        draw::set_draw_color(col);
+        draw::draw_rectf(
+            cx - body_w / 2,
+            open.min(close),
+            body_w,
+            i32::abs(close - open).max(1),
+        );
+    }
+
+    // the overlays on the candles
+    let price_y = |v: f64| layout.price_y(v);
+    if let Some(bands) = &studies.bollinger {
+        draw::set_draw_color(Color::from_u32(BOLLINGER_COLOR));
+        draw_series(layout, prices.len(), |i| bands[i].map(|b| b.0), price_y);
+        draw_series(layout, prices.len(), |i| bands[i].map(|b| b.2), price_y);
+        draw::set_line_style(LineStyle::Dash, 1);
+        draw_series(layout, prices.len(), |i| bands[i].map(|b| b.1), price_y);
+        draw::set_line_style(LineStyle::Solid, 0);
+    }
+    if let Some(sma) = &studies.sma {
+        draw::set_draw_color(Color::from_u32(SMA_COLOR));
+        draw_series(layout, prices.len(), |i| sma[i], price_y);
+    }
+    if let Some(ema) = &studies.ema {
+        draw::set_draw_color(Color::from_u32(EMA_COLOR));
+        draw_series(layout, prices.len(), |i| ema[i], price_y);
+    }
+    draw::pop_clip();
+
+    // the volume histogram, scaled to the highest visible volume
+    if let Some(pane) = layout.volume {
+        let max = prices[range.clone()]
+            .iter()
+            .map(|p| p.volume)
+            .max()
+            .unwrap_or_default()
+            .max(1) as f64;
+        draw_tick(layout, pane.y, &format_volume(max));
+        draw::push_clip(layout.x, pane.y, layout.w, pane.h);
+        let bar_w = ((layout.step() * 0.8) as i32).max(1);
+        for idx in range.clone() {
+            let elem = &prices[idx];
+            let top = pane.value_y(elem.volume as f64, max);
+            let col = if elem.close < elem.open {
+                VOLUME_DOWN_COLOR
+            } else {
+                VOLUME_UP_COLOR
+            };
+            draw::set_draw_color(Color::from_u32(col));
+            draw::draw_rectf(
+                layout.candle_x(idx) - bar_w / 2,
+                top,
+                bar_w,
+                pane.y + pane.h - top,
+            );
+        }
+        draw::pop_clip();
+    }
+
+    // the relative strength index, with its overbought and oversold levels
+    if let (Some(pane), Some(rsi)) = (layout.rsi, &studies.rsi) {
+        for level in [30., 70.] {
+            draw_tick(layout, pane.value_y(level, 100.), &format!("{}", level));
+        }
+        draw::push_clip(layout.x, pane.y, layout.w, pane.h);
+        draw::set_draw_color(Color::from_u32(RSI_COLOR));
+        draw_series(layout, prices.len(), |i| rsi[i], |v| pane.value_y(v, 100.));
+        draw::pop_clip();
+    }
+    draw::pop_clip();
+}
+
+// draw the gridlines, the price axis with its unit and the date axis, under every pane
+fn draw_axes(dates: &[NaiveDateTime], layout: &Layout, unit: &str) {
+    let bottom = layout.bottom();
+    draw::set_font(Font::Helvetica, 10);
+
+    // horizontal gridlines and the price axis
+    let ticks = nice_ticks(
+        layout.low,
+        layout.high,
+        (layout.h as f64 / MIN_TICK_SPACING) as usize,
+    );
+    let tick_step = if ticks.len() > 1 {
+        ticks[1] - ticks[0]
+    } else {
+        1.
+    };
+    for tick in &ticks {
+        draw_tick(
+            layout,
+            layout.price_y(*tick),
+            &(format_price(*tick, tick_step) + unit),
+        );
+    }
+
+    // vertical gridlines and the date axis
+    if !dates.is_empty() {
+        for (idx, label) in date_labels(dates, layout) {
+            let lx = layout.candle_x(idx);
+            draw::set_draw_color(Color::from_u32(GRID_COLOR));
+            draw::draw_line(lx, layout.y, lx, bottom);
+            draw::set_draw_color(Color::from_u32(AXIS_COLOR));
+            draw::draw_line(lx, bottom, lx, bottom + 4);
+            draw::draw_text2(&label, lx + 2, bottom + 4, 60, 14, Align::Left);
+        }
+    }
+
+    draw::set_draw_color(Color::from_u32(AXIS_COLOR));
+    draw::draw_line(layout.x + layout.w, layout.y, layout.x + layout.w, bottom);
+    for pane_bottom in [
+        Some(layout.y + layout.h),
+        layout.volume.map(|p| p.y + p.h),
+        layout.rsi.map(|p| p.y + p.h),
+    ]
+    .into_iter()
+    .flatten()
+    {
+        draw::draw_line(layout.x, pane_bottom, layout.x + layout.w, pane_bottom);
+    }
+}
+
+/// Draws the compared series as lines of their change from the first visible date, with a legend
+pub fn draw_comparison(comparison: &Comparison, layout: &Layout) {
+    let bottom = layout.bottom();
+    draw::push_clip(
+        layout.x,
+        layout.y,
+        layout.w + PRICE_AXIS_W,
+        bottom - layout.y + DATE_AXIS_H,
+    );
+    draw_axes(&comparison.dates, layout, "%");
+
+    let count = comparison.dates.len();
+    let range = layout.view.range(count);
+    let values = comparison.normalized(range.clone());
+    draw::push_clip(layout.x, layout.y, layout.w, layout.h);
+    draw::set_draw_color(Color::from_u32(AXIS_COLOR));
+    draw::set_line_style(LineStyle::Dash, 1);
+    let zero = layout.price_y(0.);
+    draw::draw_line(layout.x, zero, layout.x + layout.w, zero);
+    for (i, series) in values.iter().enumerate() {
+        // join the dates a symbol has no row for, up to its nearest values beyond the view
+        let first = (0..range.start)
+            .rev()
+            .find(|&idx| series[idx].is_some())
+            .unwrap_or(range.start);
+        let last = (range.end..count)
+            .find(|&idx| series[idx].is_some())
+            .map_or(range.end, |idx| idx + 1);
+        draw::set_draw_color(Color::from_u32(SERIES_COLORS[i % SERIES_COLORS.len()]));
+        draw::set_line_style(LineStyle::Solid, 2);
+        draw::begin_line();
+        for (idx, v) in series.iter().enumerate().take(last).skip(first) {
+            if let Some(v) = v {
+                draw::vertex(layout.candle_x(idx) as f64, layout.price_y(*v) as f64);
+            }
+        }
+        draw::end_line();
+    }
+    draw::set_line_style(LineStyle::Solid, 0);
+
+    // the legend, with the change of each series at the last visible date
+    draw::set_font(Font::Helvetica, 11);
+    let rows: Vec<String> = comparison
+        .series
+        .iter()
+        .zip(&values)
+        .map(
+            |(series, values)| match values[range.clone()].iter().rev().flatten().next() {
+                Some(v) => format!("{}  {:+.2}%", series.name, v),
+                None => series.name.clone(),
+            },
+        )
+        .collect();
+    let lw = rows
+        .iter()
+        .map(|r| draw::measure(r, false).0)
+        .max()
+        .unwrap_or(0)
+        + 30;
+    let lh = rows.len() as i32 * 16 + 8;
+    let (lx, ly) = (layout.x + 8, layout.y + 8);
+    draw::set_draw_color(Color::from_u32(TOOLTIP_COLOR));
+    draw::draw_rectf(lx, ly, lw, lh);
+    draw::set_draw_color(Color::from_u32(AXIS_COLOR));
+    draw::draw_rect(lx, ly, lw, lh);
+    for (i, row) in rows.iter().enumerate() {
+        let ry = ly + 4 + i as i32 * 16;
+        draw::set_draw_color(Color::from_u32(SERIES_COLORS[i % SERIES_COLORS.len()]));
+        draw::draw_rectf(lx + 6, ry + 5, 14, 4);
+        draw::set_draw_color(Color::White);
+        draw::draw_text2(row, lx + 24, ry, lw - 28, 16, Align::Left);
+    }
+    draw::pop_clip();
+    draw::pop_clip();
+}
+
+// draw a horizontal gridline with its label on the price axis
+fn draw_tick(layout: &Layout, ty: i32, label: &str) {
+    draw::set_draw_color(Color::from_u32(GRID_COLOR));
+    draw::draw_line(layout.x, ty, layout.x + layout.w, ty);
+    draw::set_draw_color(Color::from_u32(AXIS_COLOR));
+    draw::draw_line(layout.x + layout.w, ty, layout.x + layout.w + 4, ty);
+    draw::draw_text2(
+        label,
+        layout.x + layout.w + 6,
+        ty - 6,
+        PRICE_AXIS_W - 8,
+        12,
+        Align::Left,
+    );
+}
+
+// draw a line through the values of the visible candles, and one beyond on each side,
+// leaving gaps where there are no values
+fn draw_series<V: Fn(usize) -> Option<f64>, Y: Fn(f64) -> i32>(
+    layout: &Layout,
+    count: usize,
+    value: V,
+    y: Y,
+) {
+    let range = layout.view.range(count);
+    let mut drawing = false;
+    for idx in range.start.saturating_sub(1)..(range.end + 1).min(count) {
+        match value(idx) {
+            Some(v) => {
+                if !drawing {
+                    draw::begin_line();
+                    drawing = true;
+                }
+                draw::vertex(layout.candle_x(idx) as f64, y(v) as f64);
+            }
+            None if drawing => {
+                draw::end_line();
+                drawing = false;
+            }
+            None => (),
+        }
+    }
+    if drawing {
+        draw::end_line();
+    }
+}
+
+// format a volume with a K, M or B suffix
+fn format_volume(v: f64) -> String {
+    match v {
+        v if v >= 1e9 => format!("{:.1}B", v / 1e9),
+        v if v >= 1e6 => format!("{:.1}M", v / 1e6),
+        v if v >= 1e3 => format!("{:.1}K", v / 1e3),
+        v => format!("{}", v),
+    }
+}
+
+// the visible candle nearest to a horizontal position
+fn snap(layout: &Layout, count: usize, mx: i32) -> Option<usize> {
+    let range = layout.view.range(count);
+    (!range.is_empty())
+        .then(|| (layout.x_index(mx).floor().max(0.) as usize).clamp(range.start, range.end - 1))
+}
+
+// draw crosshair lines snapped to the nearest candle, with its values in a tooltip
+fn draw_crosshair(prices: &[Price], layout: &Layout, mx: i32, my: i32) {
+    let Some(idx) = snap(layout, prices.len(), mx) else {
+        return;
+    };
+    let elem = &prices[idx];
+    let price = layout.y_price(my);
+    let txt = format!(
+        "Date: {}\nOpen: {}\nHigh: {}\nLow: {}\nClose: {}\nVolume: {}",
+        format_date(elem.date),
+        elem.open,
+        elem.high,
+        elem.low,
+        elem.close,
+        elem.volume
+    );
+    draw_cursor(
+        layout,
+        layout.candle_x(idx),
+        (mx, my),
+        &format_price(price, price.abs() / 1000.),
+        &txt,
+    );
+}
+
+// draw crosshair lines snapped to the nearest date, with the change of each series in a tooltip
+fn draw_comparison_crosshair(comparison: &Comparison, layout: &Layout, mx: i32, my: i32) {
+    let Some(idx) = snap(layout, comparison.dates.len(), mx) else {
+        return;
+    };
+    let values = comparison.normalized(layout.view.range(comparison.dates.len()));
+    let mut txt = format!("Date: {}", format_date(comparison.dates[idx]));
+    for (series, values) in comparison.series.iter().zip(&values) {
+        match values[idx] {
+            Some(v) => txt += &format!("\n{}: {:+.2}%", series.name, v),
+            None => txt += &format!("\n{}: -", series.name),
+        }
+    }
+    draw_cursor(
+        layout,
+        layout.candle_x(idx),
+        (mx, my),
+        &format!("{:+.2}%", layout.y_price(my)),
+        &txt,
+    );
+}
+
+// draw dashed lines through the mouse position and the snapped x, the value under the mouse
+// on the price axis, and a tooltip
+fn draw_cursor(layout: &Layout, cx: i32, (mx, my): (i32, i32), value: &str, txt: &str) {
+    let bottom = layout.bottom();
+    draw::push_clip(
+        layout.x,
+        layout.y,
+        layout.w + PRICE_AXIS_W,
+        bottom - layout.y + DATE_AXIS_H,
+    );
+    draw::set_draw_color(Color::from_u32(CROSSHAIR_COLOR));
+    draw::set_line_style(LineStyle::Dash, 1);
+    draw::draw_line(cx, layout.y, cx, bottom);
+    draw::draw_line(layout.x, my, layout.x + layout.w, my);
+    draw::set_line_style(LineStyle::Solid, 0);
+
+    // the value under the mouse, on the price axis
+    if my < layout.y + layout.h {
+        draw_price_label(layout, my, value);
+    }
+
+    draw::set_font(Font::Courier, 11);
+    let (tw, th) = draw::measure(txt, false);
+    let (tw, th) = (tw + 12, th + 8);
+    // keep the tooltip inside the plot
+    let tx = if mx + 12 + tw > layout.x + layout.w {
+        mx - 12 - tw
+    } else {
+        mx + 12
+    };
+    let ty = if my + 12 + th > bottom {
+        my - 12 - th
+    } else {
+        my + 12
+    };
+    draw::set_draw_color(Color::from_u32(TOOLTIP_COLOR));
+    draw::draw_rectf(tx, ty, tw, th);
+    draw::set_draw_color(Color::from_u32(AXIS_COLOR));
+    draw::draw_rect(tx, ty, tw, th);
+    draw::set_draw_color(Color::White);
+    draw::draw_text2(
+        txt,
+        tx + 6,
+        ty + 4,
+        tw - 12,
+        th - 8,
+        Align::Left | Align::Inside,
+    );
+    draw::pop_clip();
+}
+
+// draw a label at a vertical position on the price axis
+fn draw_price_label(layout: &Layout, my: i32, label: &str) {
+    draw::set_font(Font::Helvetica, 10);
+    draw::set_draw_color(Color::from_u32(CROSSHAIR_COLOR));
+    draw::draw_rectf(layout.x + layout.w + 1, my - 7, PRICE_AXIS_W - 1, 14);
+    draw::set_draw_color(Color::White);
+    draw::draw_text2(
+        label,
+        layout.x + layout.w + 6,
+        my - 7,
+        PRICE_AXIS_W - 8,
+        14,
+        Align::Left,
+    );
+}
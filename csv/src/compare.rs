@@ -0,0 +1,62 @@
+use crate::loader::Price;
+use chrono::NaiveDateTime;
+use std::ops::Range;
+
+/// The closing prices of a symbol, aligned on the dates of a `Comparison`
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub name: String,
+    /// The close at each date of the comparison, None where the symbol has no row
+    pub closes: Vec<Option<f64>>,
+}
+
+/// Several symbols aligned on the union of their dates, so dates missing from one symbol
+/// don't shift the others
+#[derive(Debug, Clone, Default)]
+pub struct Comparison {
+    pub dates: Vec<NaiveDateTime>,
+    pub series: Vec<Series>,
+}
+
+impl Comparison {
+    /// Aligns the prices of each symbol on the dates found in any of them
+    pub fn new(symbols: Vec<(String, Vec<Price>)>) -> Self {
+        let mut dates: Vec<NaiveDateTime> = symbols
+            .iter()
+            .flat_map(|(_, prices)| prices.iter().map(|p| p.date))
+            .collect();
+        dates.sort();
+        dates.dedup();
+        let series = symbols
+            .into_iter()
+            .map(|(name, prices)| {
+                let mut closes = vec![None; dates.len()];
+                for p in prices {
+                    if let Ok(idx) = dates.binary_search(&p.date) {
+                        closes[idx] = Some(p.close);
+                    }
+                }
+                Series { name, closes }
+            })
+            .collect();
+        Self { dates, series }
+    }
+
+    /// The percent change of each series from its first close in the visible range,
+    /// series without a close in the range have no values
+    pub fn normalized(&self, range: Range<usize>) -> Vec<Vec<Option<f64>>> {
+        self.series
+            .iter()
+            .map(|s| {
+                let base = s.closes[range.clone()].iter().flatten().next().copied();
+                s.closes
+                    .iter()
+                    .map(|c| match (c, base) {
+                        (Some(c), Some(base)) if base != 0. => Some((c / base - 1.) * 100.),
+                        _ => None,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,268 @@
+use crate::export::{ExportSettings, Format};
+use crate::loader::{ColumnMap, Rejected, FIELDS};
+use crate::stream::{Feed, INTERVALS};
+use fltk::{prelude::*, *};
+use std::path::Path;
+use std::{cell::RefCell, rc::Rc};
+
+/// Asks the user which column holds each field, returns None if cancelled
+pub fn column_dialog(headers: &csv::StringRecord, map: &ColumnMap) -> Option<ColumnMap> {
+    modal_dialog("Map columns", 320, FIELDS.len() as i32 * 40 + 70, |col| {
+        let mut choices = vec![];
+        for (field, name) in FIELDS.iter().enumerate() {
+            let mut row = group::Flex::default().row();
+            let label = frame::Frame::default().with_label(name);
+            let mut choice = menu::Choice::default();
+            choice.add_choice("(none)");
+            for h in headers {
+                choice.add_choice(&menu_label(h));
+            }
+            choice.set_value(map.columns[field].map_or(0, |c| c as i32 + 1));
+            row.end();
+            row.fixed(&label, 80);
+            col.fixed(&row, 30);
+            choices.push(choice);
+        }
+        move || {
+            let mut map = ColumnMap::default();
+            for (field, choice) in choices.iter().enumerate() {
+                map.columns[field] = match choice.value() {
+                    v if v > 0 => Some(v as usize - 1),
+                    _ => None,
+                };
+            }
+            Some(map)
+        }
+    })
+}
+
+/// Lists the rows of a file which couldn't be loaded
+pub fn rejected_dialog(name: &str, loaded: usize, rejected: &[Rejected]) {
+    let mut wind = window::Window::default()
+        .with_size(500, 300)
+        .with_label("Rejected rows");
+    let mut col = group::Flex::default_fill().column();
+    col.set_margin(10);
+    let summary = frame::Frame::default().with_label(&format!(
+        "{}: {} rows loaded, {} rejected",
+        name,
+        loaded,
+        rejected.len()
+    ));
+    let mut browser = browser::Browser::default();
+    let mut row = group::Flex::default().row();
+    frame::Frame::default();
+    let mut ok = button::Button::default().with_label("Ok");
+    row.end();
+    row.fixed(&ok, 80);
+    col.fixed(&summary, 30);
+    col.fixed(&row, 30);
+    col.end();
+    wind.end();
+    wind.make_modal(true);
+    wind.show();
+
+    for r in rejected {
+        browser.add(&format!("line {}: {}", r.line, r.reason));
+    }
+
+    ok.set_callback({
+        let mut wind = wind.clone();
+        move |_| wind.hide()
+    });
+
+    while wind.shown() {
+        app::wait();
+    }
+}
+
+/// Asks the user for the format and the pixel size of an exported chart, returns None if cancelled
+pub fn export_dialog(settings: &ExportSettings) -> Option<ExportSettings> {
+    modal_dialog("Export chart", 320, 190, |col| {
+        let row = labeled_row("Format");
+        let mut format = menu::Choice::default();
+        for f in Format::ALL {
+            format.add_choice(f.name());
+        }
+        format.set_value(
+            Format::ALL
+                .iter()
+                .position(|f| *f == settings.format)
+                .unwrap_or(0) as i32,
+        );
+        row.end();
+        col.fixed(&row, 30);
+        let mut sizes = vec![];
+        for (name, value) in [("Width", settings.w), ("Height", settings.h)] {
+            let row = labeled_row(name);
+            let mut size = misc::Spinner::default();
+            size.set_type(misc::SpinnerType::Int);
+            size.set_range(100., 8000.);
+            size.set_step(10.);
+            size.set_value(value as f64);
+            row.end();
+            col.fixed(&row, 30);
+            sizes.push(size);
+        }
+        move || {
+            Some(ExportSettings {
+                format: Format::ALL[format.value().max(0) as usize],
+                w: sizes[0].value() as i32,
+                h: sizes[1].value() as i32,
+            })
+        }
+    })
+}
+
+/// The settings of a live stream chosen in the live dialog
+#[derive(Debug, Clone)]
+pub struct LiveSettings {
+    pub feed: Feed,
+    /// The path of a file or a Unix socket, or the address of a TCP feed
+    pub address: String,
+    /// The candle interval in seconds, None to keep the rows as they're sent
+    pub interval: Option<i64>,
+}
+
+/// Asks the user where to stream rows from, the given file being offered first.
+/// returns None if cancelled
+pub fn live_dialog(file: Option<&Path>) -> Option<LiveSettings> {
+    modal_dialog("Live data", 400, 150, |col| {
+        let row = labeled_row("Source");
+        let mut feed = menu::Choice::default();
+        for f in Feed::ALL {
+            feed.add_choice(f.name());
+        }
+        feed.set_value(0);
+        row.end();
+        col.fixed(&row, 30);
+        let mut row = labeled_row("Address");
+        let mut address = input::Input::default();
+        address.set_value(
+            &file
+                .map(|f| f.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+        );
+        let mut browse = button::Button::default().with_label("@fileopen");
+        row.end();
+        row.fixed(&browse, 30);
+        col.fixed(&row, 30);
+        let row = labeled_row("Candles");
+        let mut interval = menu::Choice::default();
+        for (name, _) in INTERVALS {
+            interval.add_choice(name);
+        }
+        interval.set_value(0);
+        row.end();
+        col.fixed(&row, 30);
+
+        // suggest a default address for the feed
+        feed.set_callback({
+            let mut address = address.clone();
+            let mut browse = browse.clone();
+            let file = file.map(|f| f.to_string_lossy().into_owned());
+            move |c| {
+                let f = Feed::ALL[c.value().max(0) as usize];
+                address.set_value(&match f {
+                    Feed::File => file.clone().unwrap_or_default(),
+                    Feed::Tcp => "127.0.0.1:9000".to_string(),
+                    Feed::Unix => "/tmp/prices.sock".to_string(),
+                });
+                if f == Feed::File {
+                    browse.activate();
+                } else {
+                    browse.deactivate();
+                }
+            }
+        });
+        browse.set_callback({
+            let mut address = address.clone();
+            move |_| {
+                let mut dlg =
+                    dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+                dlg.set_filter("*.{csv,tsv,txt}");
+                dlg.show();
+                let path = dlg.filename();
+                if !path.as_os_str().is_empty() {
+                    address.set_value(&path.to_string_lossy());
+                }
+            }
+        });
+        move || {
+            let value = address.value();
+            (!value.trim().is_empty()).then(|| LiveSettings {
+                feed: Feed::ALL[feed.value().max(0) as usize],
+                address: value.trim().to_string(),
+                interval: INTERVALS[interval.value().max(0) as usize].1,
+            })
+        }
+    })
+}
+
+// show a modal dialog with Ok and Cancel buttons under the fields `build` adds to its
+// column. `build` returns the closure reading the fields once Ok is pressed, which may
+// still refuse them with None. returns None if cancelled
+fn modal_dialog<T, R: FnOnce() -> Option<T>>(
+    label: &str,
+    w: i32,
+    h: i32,
+    build: impl FnOnce(&mut group::Flex) -> R,
+) -> Option<T> {
+    let mut wind = window::Window::default().with_size(w, h).with_label(label);
+    let mut col = group::Flex::default_fill().column();
+    col.set_margin(10);
+    let read = build(&mut col);
+    let mut row = group::Flex::default().row();
+    frame::Frame::default();
+    let mut ok = button::Button::default().with_label("Ok");
+    let mut cancel = button::Button::default().with_label("Cancel");
+    row.end();
+    row.fixed(&ok, 80);
+    row.fixed(&cancel, 80);
+    col.fixed(&row, 30);
+    col.end();
+    wind.end();
+    wind.make_modal(true);
+    wind.show();
+
+    let accepted = Rc::from(RefCell::from(false));
+    ok.set_callback({
+        let accepted = accepted.clone();
+        let mut wind = wind.clone();
+        move |_| {
+            *accepted.borrow_mut() = true;
+            wind.hide();
+        }
+    });
+    cancel.set_callback({
+        let mut wind = wind.clone();
+        move |_| wind.hide()
+    });
+
+    while wind.shown() {
+        app::wait();
+    }
+
+    let accepted = *accepted.borrow();
+    if accepted {
+        read()
+    } else {
+        None
+    }
+}
+
+// start a dialog row with a label, the caller adds the field and ends the row
+fn labeled_row(name: &str) -> group::Flex {
+    let mut row = group::Flex::default().row();
+    let label = frame::Frame::default().with_label(name);
+    row.fixed(&label, 80);
+    row
+}
+
+// escape the characters which have a special meaning in menu labels
+fn menu_label(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('/', "\\/")
+        .replace('&', "&&")
+        .replace('|', " ")
+}
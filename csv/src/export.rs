@@ -0,0 +1,85 @@
+use crate::chart::Chart;
+use ::image::{ImageBuffer, ImageError, ImageFormat, RgbImage};
+use fltk::{prelude::*, *};
+use std::path::Path;
+
+/// The file formats a chart can be exported to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Png,
+    Jpeg,
+    Svg,
+}
+
+impl Format {
+    /// The formats in the order they're offered
+    pub const ALL: [Format; 3] = [Format::Png, Format::Jpeg, Format::Svg];
+
+    /// The name shown in the export dialog
+    pub fn name(&self) -> &'static str {
+        match self {
+            Format::Png => "PNG",
+            Format::Jpeg => "JPEG",
+            Format::Svg => "SVG",
+        }
+    }
+
+    /// The usual file extension of the format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::Svg => "svg",
+        }
+    }
+
+    /// The format of a file extension, ignoring case
+    pub fn from_extension(ext: &str) -> Option<Format> {
+        match ext.to_ascii_lowercase().as_str() {
+            "png" => Some(Format::Png),
+            "jpg" | "jpeg" => Some(Format::Jpeg),
+            "svg" => Some(Format::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// The settings of an export, the size being that of the image rather than the window
+#[derive(Debug, Clone, Copy)]
+pub struct ExportSettings {
+    pub format: Format,
+    pub w: i32,
+    pub h: i32,
+}
+
+/// Writes the chart to a file, SVG files get the candles as vector shapes
+pub fn export(
+    chart: &Chart,
+    symbol: &str,
+    settings: &ExportSettings,
+    path: &Path,
+) -> Result<(), ImageError> {
+    let ExportSettings { format, w, h } = *settings;
+    if format == Format::Svg {
+        // fail early, the surface doesn't report files it can't write
+        std::fs::File::create(path)?;
+        let sur = surface::SvgFileSurface::new(w, h, path);
+        surface::SvgFileSurface::push_current(&sur);
+        chart.render(symbol, w, h);
+        surface::SvgFileSurface::pop_current();
+        // the file is completed when the surface is dropped
+        return Ok(());
+    }
+    let sur = surface::ImageSurface::new(w, h, false);
+    surface::ImageSurface::push_current(&sur);
+    chart.render(symbol, w, h);
+    let img = sur.image().unwrap();
+    surface::ImageSurface::pop_current();
+    let mut imgbuf: RgbImage = ImageBuffer::new(w as _, h as _);
+    imgbuf.copy_from_slice(&img.to_rgb_data());
+    let format = match format {
+        Format::Jpeg => ImageFormat::Jpeg,
+        _ => ImageFormat::Png,
+    };
+    imgbuf.save_with_format(path, format)
+}
@@ -0,0 +1,91 @@
+/// The studies drawn along with the candles, None when turned off
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Indicators {
+    /// A histogram of the volume under the candles
+    pub volume: bool,
+    /// The period of the simple moving average
+    pub sma: Option<usize>,
+    /// The period of the exponential moving average
+    pub ema: Option<usize>,
+    /// The period and the width, in standard deviations, of the Bollinger Bands
+    pub bollinger: Option<(usize, f64)>,
+    /// The period of the relative strength index, drawn in its own pane
+    pub rsi: Option<usize>,
+}
+
+/// The simple moving average of the values, None until there are enough values
+pub fn sma(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 {
+        return out;
+    }
+    let mut sum = 0.;
+    for (i, v) in values.iter().enumerate() {
+        sum += v;
+        if i >= period {
+            sum -= values[i - period];
+        }
+        if i + 1 >= period {
+            out[i] = Some(sum / period as f64);
+        }
+    }
+    out
+}
+
+/// The exponential moving average of the values, seeded with the simple average of the first period
+pub fn ema(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 || values.len() < period {
+        return out;
+    }
+    let alpha = 2. / (period as f64 + 1.);
+    let mut avg = values[..period].iter().sum::<f64>() / period as f64;
+    out[period - 1] = Some(avg);
+    for i in period..values.len() {
+        avg += alpha * (values[i] - avg);
+        out[i] = Some(avg);
+    }
+    out
+}
+
+/// The lower band, middle band and upper band of the Bollinger Bands,
+/// `k` standard deviations around the simple moving average
+pub fn bollinger(values: &[f64], period: usize, k: f64) -> Vec<Option<(f64, f64, f64)>> {
+    sma(values, period)
+        .into_iter()
+        .enumerate()
+        .map(|(i, mid)| {
+            let mid = mid?;
+            let window = &values[i + 1 - period..=i];
+            let var = window.iter().map(|v| (v - mid).powi(2)).sum::<f64>() / period as f64;
+            let dev = k * var.sqrt();
+            Some((mid - dev, mid, mid + dev))
+        })
+        .collect()
+}
+
+/// The relative strength index of the values, from 0 to 100, using Wilder's smoothing
+pub fn rsi(values: &[f64], period: usize) -> Vec<Option<f64>> {
+    let mut out = vec![None; values.len()];
+    if period == 0 || values.len() <= period {
+        return out;
+    }
+    let change = |i: usize| values[i] - values[i - 1];
+    let mut gain = (1..=period).map(|i| change(i).max(0.)).sum::<f64>() / period as f64;
+    let mut loss = (1..=period).map(|i| (-change(i)).max(0.)).sum::<f64>() / period as f64;
+    let index = |gain: f64, loss: f64| {
+        if loss == 0. {
+            100.
+        } else {
+            100. - 100. / (1. + gain / loss)
+        }
+    };
+    out[period] = Some(index(gain, loss));
+    for i in period + 1..values.len() {
+        let p = period as f64;
+        gain = (gain * (p - 1.) + change(i).max(0.)) / p;
+        loss = (loss * (p - 1.) + (-change(i)).max(0.)) / p;
+        out[i] = Some(index(gain, loss));
+    }
+    out
+}
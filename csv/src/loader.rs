@@ -0,0 +1,204 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime};
+use std::io::BufRead;
+use std::path::Path;
+
+/// The fields of a `Price`, in the order they're mapped
+pub const FIELDS: [&str; 6] = ["Date", "Open", "High", "Low", "Close", "Volume"];
+
+// header names recognized for each field, compared in lowercase
+const ALIASES: [&[&str]; 6] = [
+    &["date", "day", "time", "timestamp", "datetime"],
+    &["open", "o", "opening", "open price"],
+    &["high", "h", "max", "high price"],
+    &["low", "l", "min", "low price"],
+    &["close", "c", "last", "price", "close price", "closing"],
+    &["volume", "vol", "v", "qty", "quantity"],
+];
+
+// date formats tried in order, ISO first then US
+const DATE_FORMATS: [&str; 4] = ["%Y-%m-%d", "%Y/%m/%d", "%m/%d/%Y", "%m-%d-%Y"];
+// time formats of intraday rows
+const TIME_FORMATS: [&str; 2] = ["%H:%M:%S%.f", "%H:%M"];
+
+#[derive(Debug, Clone)]
+pub struct Price {
+    /// The start of the candle, at midnight for daily candles
+    pub date: NaiveDateTime,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: usize,
+}
+
+/// A row which couldn't be loaded
+#[derive(Debug, Clone)]
+pub struct Rejected {
+    pub line: u64,
+    pub reason: String,
+}
+
+/// The rows of a CSV file, sorted by date, and the rows which were rejected
+#[derive(Debug, Default)]
+pub struct Loaded {
+    pub prices: Vec<Price>,
+    pub rejected: Vec<Rejected>,
+}
+
+/// Maps the fields of a `Price` onto the columns of a CSV file
+#[derive(Debug, Clone, Default)]
+pub struct ColumnMap {
+    /// The column of each of the `FIELDS`, the volume is optional
+    pub columns: [Option<usize>; 6],
+}
+
+impl ColumnMap {
+    /// Guesses the columns from the header names
+    pub fn detect(headers: &csv::StringRecord) -> Self {
+        let mut map = Self::default();
+        for (field, aliases) in ALIASES.iter().enumerate() {
+            map.columns[field] = headers
+                .iter()
+                .position(|h| aliases.contains(&h.trim().to_lowercase().as_str()));
+        }
+        map
+    }
+
+    /// Whether every required field has a column
+    pub fn is_complete(&self) -> bool {
+        self.columns[..5].iter().all(Option::is_some)
+    }
+
+    /// Uses the close column for the open, high and low when they're missing, as with tick feeds
+    pub fn with_ticks(mut self) -> Self {
+        for field in 1..4 {
+            self.columns[field] = self.columns[field].or(self.columns[4]);
+        }
+        self
+    }
+
+    /// Reads a `Price` from a record of a file with the given delimiter, or the reason it
+    /// can't be read
+    pub fn parse(&self, record: &csv::StringRecord, delimiter: u8) -> Result<Price, String> {
+        let field = |i: usize| {
+            self.columns[i]
+                .and_then(|col| record.get(col))
+                .map(str::trim)
+                .ok_or_else(|| format!("{} is missing", FIELDS[i]))
+        };
+        let number = |i: usize| {
+            let s = field(i)?;
+            parse_number(s, delimiter).ok_or_else(|| format!("{}: {:?} is not a number", FIELDS[i], s))
+        };
+        let date = field(0)?;
+        let date = parse_date(date).ok_or_else(|| format!("Date: {:?} is not a date", date))?;
+        let price = Price {
+            date,
+            open: number(1)?,
+            high: number(2)?,
+            low: number(3)?,
+            close: number(4)?,
+            volume: match self.columns[5] {
+                Some(_) => number(5)?.max(0.) as usize,
+                None => 0,
+            },
+        };
+        if price.high < price.low {
+            Err(format!("High {} is below Low {}", price.high, price.low))
+        } else {
+            Ok(price)
+        }
+    }
+}
+
+/// Guesses the delimiter from the header line, one of comma, semicolon or tab
+pub fn sniff_delimiter<P: AsRef<Path>>(path: P) -> std::io::Result<u8> {
+    let mut line = String::new();
+    std::io::BufReader::new(std::fs::File::open(path)?).read_line(&mut line)?;
+    Ok([b',', b';', b'\t']
+        .into_iter()
+        .max_by_key(|d| line.bytes().filter(|b| b == d).count())
+        .filter(|d| line.as_bytes().contains(d))
+        .unwrap_or(b','))
+}
+
+/// Reads the header names of a CSV file
+pub fn read_headers<P: AsRef<Path>>(path: P, delimiter: u8) -> Result<csv::StringRecord, csv::Error> {
+    csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_path(path)?
+        .headers()
+        .cloned()
+}
+
+/// Reads the prices of a CSV file, rows which can't be parsed are rejected with their line number
+pub fn read_prices<P: AsRef<Path>>(
+    path: P,
+    delimiter: u8,
+    map: &ColumnMap,
+) -> Result<Loaded, csv::Error> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_path(path)?;
+    let mut loaded = Loaded::default();
+    for result in rdr.records() {
+        match result {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line());
+                match map.parse(&record, delimiter) {
+                    Ok(price) => loaded.prices.push(price),
+                    Err(reason) => loaded.rejected.push(Rejected { line, reason }),
+                }
+            }
+            Err(e) if e.is_io_error() => return Err(e),
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                loaded.rejected.push(Rejected {
+                    line,
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+    loaded.prices.sort_by_key(|p| p.date);
+    Ok(loaded)
+}
+
+// parse a number. commas are decimal commas in semicolon delimited files, and thousands
+// separators otherwise, e.g. a quoted "1,234"
+fn parse_number(s: &str, delimiter: u8) -> Option<f64> {
+    let number = match s.contains(',') {
+        false => s.to_string(),
+        true if delimiter == b';' => s.replace(',', "."),
+        true => strip_grouping(s)?,
+    };
+    number.parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+// remove the thousands separators of a number, None if they aren't between groups of 3 digits
+fn strip_grouping(s: &str) -> Option<String> {
+    let (int, rest) = s.find(['.', 'e', 'E']).map_or((s, ""), |i| s.split_at(i));
+    let digits = int.trim_start_matches(['-', '+']);
+    let mut groups = digits.split(',');
+    let first = groups.next()?;
+    let grouped = (1..=3).contains(&first.len()) && groups.all(|g| g.len() == 3);
+    grouped.then(|| format!("{}{}", int.replace(',', ""), rest))
+}
+
+// parse an ISO or US date with an optional time, or a unix timestamp in seconds.
+// anything after the time, such as a timezone, is ignored, and dates without a time are at midnight
+fn parse_date(s: &str) -> Option<NaiveDateTime> {
+    if s.len() >= 9 && s.bytes().all(|b| b.is_ascii_digit()) {
+        return DateTime::from_timestamp(s.parse().ok()?, 0).map(|d| d.naive_utc());
+    }
+    let (date, time) = s.split_once(['T', ' ']).unwrap_or((s, ""));
+    let date = DATE_FORMATS
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(date, f).ok())?;
+    let time = TIME_FORMATS
+        .iter()
+        .find_map(|f| NaiveTime::parse_and_remainder(time.trim(), f).ok())
+        .map_or(NaiveTime::MIN, |(t, _)| t);
+    Some(date.and_time(time))
+}
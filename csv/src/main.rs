@@ -1,30 +1,26 @@
-use ::image::{ImageBuffer, RgbImage};
 use fltk::{
-    enums::*,
     prelude::*,
     *,
 };
-use serde::Deserialize;
+use compare::Comparison;
+use export::{ExportSettings, Format};
+use loader::{ColumnMap, Loaded, Price, Rejected};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::{cell::RefCell, rc::Rc};
 use std::sync::Mutex;
 
 #[macro_use]
 extern crate lazy_static;
 
-#[derive(Debug, Deserialize)]
-pub struct Price {
-    #[serde(rename = "Date")]
-    date: String,
-    #[serde(rename = "Open")]
-    open: f64,
-    #[serde(rename = "High")]
-    high: f64,
-    #[serde(rename = "Low")]
-    low: f64,
-    #[serde(rename = "Close")]
-    close: f64,
-    #[serde(rename = "Volume")]
-    volume: usize,
-}
+mod chart;
+mod compare;
+mod dialogs;
+mod export;
+mod indicators;
+mod loader;
+mod panel;
+mod stream;
 
 lazy_static! {
     pub static ref PRICES: Mutex<Vec<Price>> = Mutex::new(vec![]);
@@ -41,90 +37,340 @@ fn main() {
     wind.resizable(&row);
     let mut col = group::Flex::default().column();
     let mut browser = browser::Browser::default();
+    let mut panel = panel::IndicatorPanel::default();
+    let mut open = button::Button::default()
+        .with_label("@fileopen");
     let mut btn = button::Button::default()
         .with_label("@filesave");
+    let mut live_btn = button::ToggleButton::default().with_label("Live");
+    let mut columns_btn = button::Button::default().with_label("Columns...");
     // btn.set_label_color(Color::Magenta);
     col.end();
+    col.fixed(&open, 50);
     col.fixed(&btn, 50);
-    row.fixed(&col, 100);
-    let mut frame = frame::Frame::default();
+    col.fixed(&live_btn, 30);
+    col.fixed(&columns_btn, 30);
+    col.fixed(&*panel, 180);
+    row.fixed(&col, 160);
+    let mut chart = chart::Chart::default();
     wind.end();
     wind.show();
 
-    browser.set_type(browser::BrowserType::Hold);
+    chart.set_indicators(panel.indicators());
+    panel.set_callback({
+        let mut chart = chart.clone();
+        move |p| chart.set_indicators(p.indicators())
+    });
+
+    // the paths of the browser's entries
+    let paths = Rc::from(RefCell::from(vec![]));
+    browser.set_type(browser::BrowserType::Multi);
     for file in files {
-        let entry = file.unwrap().file_name().into_string().unwrap();
-        if entry.ends_with(".csv") {
-            browser.add(&entry.strip_suffix(".csv").unwrap());
+        let path = file.unwrap().path();
+        if path.extension().is_some_and(|ext| ext == "csv") {
+            browser.add(&symbol_name(&path));
+            paths.borrow_mut().push(path);
         }
     }
 
-    frame.set_frame(FrameType::DownBox);
-    frame.set_color(Color::Black);
-
-    frame.draw(|f| {
-        let data = PRICES.lock().unwrap();
-        let mut highest = data
-            .iter()
-            .map(|elem| elem.low)
-            .collect::<Vec<f64>>()
-            .iter()
-            .cloned()
-            .fold(0. / 0., f64::max);
-        highest += (highest.to_string().len() * 10) as f64 / 3.;
-        let factor = f.h() as f64 / highest;
-        if data.len() != 0 {
-            let step = f.w() / data.len() as i32;
-            let mut idx = f.x() + step;
-            for elem in &*data {
-                let open = f.h() - (elem.open * factor) as i32;
-                let high = f.h() - (elem.high * factor) as i32;
-                let low = f.h() - (elem.low * factor) as i32;
-                let close = f.h() - (elem.close * factor) as i32;
-                draw::set_draw_color(Color::White);
-                draw::draw_line(idx, high, idx, low);
-                let col = if close > open {
-                    Color::Red
-                } else {
-                    Color::Green
-                };
-                draw::set_draw_color(col);
-                draw::draw_rectf(idx - 2, open, 4, i32::abs(close - open));
-                draw::set_draw_color(Color::White);
-                idx += step;
+    // the column maps the user confirmed, by path
+    let maps: Rc<RefCell<HashMap<PathBuf, ColumnMap>>> = Rc::from(RefCell::from(HashMap::new()));
+    // the files loaded, kept so selecting more files to compare only loads the new ones
+    let cache: Rc<RefCell<HashMap<PathBuf, Loaded>>> = Rc::from(RefCell::from(HashMap::new()));
+
+    // the name of the data being shown, and the live stream feeding it if any
+    let symbol = Rc::from(RefCell::from(String::from("chart")));
+    let live: Rc<RefCell<Option<stream::Stream>>> = Rc::from(RefCell::from(None));
+    // the rows of the live stream which couldn't be used
+    let live_rejected: Rc<RefCell<Vec<Rejected>>> = Rc::from(RefCell::from(vec![]));
+    let (s, r) = app::channel::<stream::Message>();
+
+    // export the chart, remembering the settings for the next export
+    let settings = Rc::from(RefCell::from(ExportSettings {
+        format: Format::Png,
+        w: 1200,
+        h: 800,
+    }));
+    btn.set_callback({
+        let chart = chart.clone();
+        let symbol = symbol.clone();
+        move |_| {
+            let Some(mut chosen) = dialogs::export_dialog(&settings.borrow()) else {
+                return;
+            };
+            let symbol = symbol.borrow().clone();
+            let ext = chosen.format.extension();
+            let mut dlg =
+                dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+            dlg.set_option(dialog::NativeFileChooserOptions::SaveAsConfirm);
+            dlg.set_filter(&format!("*.{}", ext));
+            dlg.set_preset_file(&format!("{}.{}", symbol, ext));
+            dlg.show();
+            let mut path = dlg.filename();
+            if path.as_os_str().is_empty() {
+                return;
+            }
+            // a typed extension of a known format wins over the chosen one
+            match path.extension().and_then(|e| e.to_str()).and_then(Format::from_extension) {
+                Some(format) => chosen.format = format,
+                None => path.as_mut_os_string().push(format!(".{}", ext)),
+            }
+            *settings.borrow_mut() = chosen;
+            if let Err(e) = export::export(&chart, &symbol, &chosen, &path) {
+                dialog::alert_default(&format!("Couldn't export to {:?}: {}", path, e));
             }
         }
     });
 
-    btn.set_callback({
-        let frame = frame.clone();
+    browser.set_callback({
+        let paths = paths.clone();
+        let maps = maps.clone();
+        let cache = cache.clone();
+        let mut chart = chart.clone();
+        let symbol = symbol.clone();
+        let live = live.clone();
+        let live_rejected = live_rejected.clone();
+        let mut live_btn = live_btn.clone();
+        move |t| {
+            // several selected files are compared
+            let selected: Vec<PathBuf> = (1..=t.size())
+                .filter(|&line| t.selected(line))
+                .filter_map(|line| paths.borrow().get((line - 1) as usize).cloned())
+                .collect();
+            if selected.is_empty() {
+                return;
+            }
+            if live.borrow().is_some() {
+                live_btn.set_value(false);
+                stop_live(&live, &live_rejected, &symbol);
+            }
+            let mut symbols = vec![];
+            for path in &selected {
+                if let Some(loaded) = cache.borrow().get(path) {
+                    symbols.push((symbol_name(path), loaded.prices.clone()));
+                    continue;
+                }
+                match load_file(path, &maps) {
+                    Ok(Some(loaded)) => {
+                        if !loaded.rejected.is_empty() {
+                            dialogs::rejected_dialog(
+                                &symbol_name(path),
+                                loaded.prices.len(),
+                                &loaded.rejected,
+                            );
+                        }
+                        symbols.push((symbol_name(path), loaded.prices.clone()));
+                        cache.borrow_mut().insert(path.clone(), loaded);
+                    }
+                    Ok(None) => return,
+                    Err(e) => {
+                        dialog::alert_default(&format!("Couldn't load {:?}: {}", path, e));
+                        return;
+                    }
+                }
+            }
+            if symbols.len() == 1 {
+                let (name, prices) = symbols.remove(0);
+                *PRICES.lock().unwrap() = prices;
+                *symbol.borrow_mut() = name;
+                chart.set_comparison(None);
+            } else {
+                let names: Vec<&str> = symbols.iter().map(|(name, _)| name.as_str()).collect();
+                *symbol.borrow_mut() = names.join(", ");
+                chart.set_comparison(Some(Comparison::new(symbols)));
+            }
+        }
+    });
+
+    // start streaming rows into the chart, or stop
+    live_btn.set_callback({
+        let mut chart = chart.clone();
+        let browser = browser.clone();
+        let paths = paths.clone();
+        let symbol = symbol.clone();
+        let live = live.clone();
+        let live_rejected = live_rejected.clone();
+        move |b| {
+            if !b.value() {
+                stop_live(&live, &live_rejected, &symbol);
+                return;
+            }
+            b.set_value(false);
+            let selected = paths.borrow().get((browser.value() - 1) as usize).cloned();
+            let Some(settings) = dialogs::live_dialog(selected.as_deref()) else {
+                return;
+            };
+            let source = match settings.feed {
+                stream::Feed::File => {
+                    let path = Path::new(&settings.address);
+                    match column_map(path, None, false) {
+                        Ok(Some((delimiter, map))) => {
+                            stream::Source::File(path.to_path_buf(), delimiter, map)
+                        }
+                        Ok(None) => return,
+                        Err(e) => {
+                            dialog::alert_default(&format!("Couldn't read {:?}: {}", path, e));
+                            return;
+                        }
+                    }
+                }
+                stream::Feed::Tcp => stream::Source::Tcp(settings.address.clone()),
+                stream::Feed::Unix => stream::Source::Unix(settings.address.clone().into()),
+            };
+            match stream::Stream::start(source, settings.interval, s) {
+                Ok(stream) => {
+                    *live.borrow_mut() = Some(stream);
+                    live_rejected.borrow_mut().clear();
+                    *symbol.borrow_mut() = match settings.feed {
+                        stream::Feed::File => symbol_name(Path::new(&settings.address)),
+                        _ => settings.address,
+                    };
+                    PRICES.lock().unwrap().clear();
+                    chart.set_comparison(None);
+                    b.set_value(true);
+                }
+                Err(e) => {
+                    dialog::alert_default(&format!("Couldn't open {}: {}", settings.address, e))
+                }
+            }
+        }
+    });
+
+    // correct the columns of the selected file, e.g. when they were detected wrongly
+    columns_btn.set_callback({
+        let mut browser = browser.clone();
+        let paths = paths.clone();
         move |_| {
-            let sur = surface::ImageSurface::new(frame.w(), frame.h(), false);
-            surface::ImageSurface::push_current(&sur);
-            draw::set_draw_color(enums::Color::White);
-            draw::draw_rectf(0, 0, frame.w(), frame.h());
-            sur.draw(&frame, 0, 0);
-            let img = sur.image().unwrap();
-            surface::ImageSurface::pop_current();
-            let mut imgbuf: RgbImage = ImageBuffer::new(frame.w() as _, frame.h() as _); // this is from the image crate
-            imgbuf.copy_from_slice(&img.to_rgb_data());
-            imgbuf.save("image.jpg").unwrap();
+            let Some(path) = paths.borrow().get((browser.value() - 1) as usize).cloned() else {
+                return;
+            };
+            let known = maps.borrow().get(&path).cloned();
+            match column_map(&path, known, true) {
+                Ok(Some((_, map))) => {
+                    // loaded again with the new columns
+                    cache.borrow_mut().remove(&path);
+                    maps.borrow_mut().insert(path, map);
+                    browser.do_callback();
+                }
+                Ok(None) => (),
+                Err(e) => dialog::alert_default(&format!("Couldn't read {:?}: {}", path, e)),
+            }
         }
     });
 
-    browser.set_callback(move |t| {
-        if let Some(file) = t.selected_text() {
-            let file = format!("historical_data/{}.csv", file);
-            let mut rdr = csv::Reader::from_reader(std::fs::File::open(file).unwrap());
-            let mut data = PRICES.lock().unwrap();
-            data.clear();
-            for result in rdr.deserialize() {
-                let price: Price = result.unwrap();
-                data.push(price);
+    // add csv files from anywhere on disk to the browser
+    open.set_callback({
+        let mut browser = browser.clone();
+        move |_| {
+            let mut dlg = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+            dlg.set_filter("*.{csv,tsv,txt}");
+            dlg.show();
+            let path = dlg.filename();
+            if path.as_os_str().is_empty() {
+                return;
+            }
+            paths.borrow_mut().push(path);
+            // refill the browser so only the new file is selected
+            browser.clear();
+            for path in paths.borrow().iter() {
+                browser.add(&symbol_name(path));
             }
-            frame.redraw();
+            browser.select(browser.size());
+            browser.do_callback();
         }
     });
 
-    app.run().unwrap();
+    while app.wait() {
+        // roll the rows which arrived into the candles, then redraw once
+        let old_count = PRICES.lock().unwrap().len();
+        let mut added = false;
+        let mut closed = None;
+        while let Some(msg) = r.recv() {
+            let live = live.borrow();
+            let Some(stream) = live.as_ref().filter(|stream| stream.sent(&msg)) else {
+                continue;
+            };
+            match msg {
+                stream::Message::Row(_, line, row) => {
+                    let mut prices = PRICES.lock().unwrap();
+                    match stream::roll_up(&mut prices, row, stream.interval()) {
+                        Ok(()) => added = true,
+                        Err(reason) => live_rejected.borrow_mut().push(Rejected { line, reason }),
+                    }
+                }
+                stream::Message::Rejected(_, rejected) => live_rejected.borrow_mut().push(rejected),
+                stream::Message::Closed(_, err) => closed = Some(err),
+            }
+        }
+        if added {
+            chart.follow(old_count);
+        }
+        if let Some(err) = closed {
+            live_btn.set_value(false);
+            stop_live(&live, &live_rejected, &symbol);
+            if let Some(err) = err {
+                dialog::alert_default(&format!("The live feed failed: {}", err));
+            }
+        }
+    }
+}
+
+// the name shown in the browser for a csv file
+fn symbol_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+// load a csv file with the columns the user confirmed for it, asking the user to map the
+// columns which couldn't be detected. returns None if the user cancels the mapping
+fn load_file(
+    path: &Path,
+    maps: &RefCell<HashMap<PathBuf, ColumnMap>>,
+) -> Result<Option<Loaded>, csv::Error> {
+    let known = maps.borrow().get(path).cloned();
+    match column_map(path, known, false)? {
+        Some((delimiter, map)) => {
+            let loaded = loader::read_prices(path, delimiter, &map)?;
+            maps.borrow_mut().insert(path.to_path_buf(), map);
+            Ok(Some(loaded))
+        }
+        None => Ok(None),
+    }
+}
+
+// find the delimiter and the columns of a csv file, from a known map or else detected,
+// asking the user to map them when `ask` is set or when some couldn't be detected.
+// returns None if the user cancels the mapping
+fn column_map(
+    path: &Path,
+    known: Option<ColumnMap>,
+    mut ask: bool,
+) -> Result<Option<(u8, ColumnMap)>, csv::Error> {
+    let delimiter = loader::sniff_delimiter(path)?;
+    let headers = loader::read_headers(path, delimiter)?;
+    let mut map = known.unwrap_or_else(|| ColumnMap::detect(&headers));
+    while ask || !map.is_complete() {
+        match dialogs::column_dialog(&headers, &map) {
+            Some(m) => map = m,
+            None => return Ok(None),
+        }
+        ask = false;
+    }
+    Ok(Some((delimiter, map)))
+}
+
+// stop the live stream, listing the rows it couldn't use
+fn stop_live(
+    live: &Rc<RefCell<Option<stream::Stream>>>,
+    rejected: &Rc<RefCell<Vec<Rejected>>>,
+    symbol: &Rc<RefCell<String>>,
+) {
+    live.borrow_mut().take();
+    let rejected = std::mem::take(&mut *rejected.borrow_mut());
+    if !rejected.is_empty() {
+        let name = symbol.borrow().clone();
+        let loaded = PRICES.lock().unwrap().len();
+        dialogs::rejected_dialog(&name, loaded, &rejected);
+    }
 }
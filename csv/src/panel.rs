@@ -0,0 +1,134 @@
+use crate::indicators::Indicators;
+use fltk::{prelude::*, *};
+use std::{cell::RefCell, rc::Rc};
+
+// the height of a row of the panel
+const ROW_H: i32 = 25;
+
+type Callback = Rc<RefCell<Option<Box<dyn FnMut(&mut IndicatorPanel)>>>>;
+
+/// Defines a panel of check buttons and spinners turning the chart's indicators on and off
+#[derive(Clone)]
+pub struct IndicatorPanel {
+    flex: group::Flex,
+    volume: button::CheckButton,
+    sma: (button::CheckButton, misc::Spinner),
+    ema: (button::CheckButton, misc::Spinner),
+    bollinger: (button::CheckButton, misc::Spinner),
+    bollinger_k: misc::Spinner,
+    rsi: (button::CheckButton, misc::Spinner),
+    cb: Callback,
+}
+
+impl Default for IndicatorPanel {
+    fn default() -> Self {
+        IndicatorPanel::new(0, 0, 0, 0, None)
+    }
+}
+
+impl IndicatorPanel {
+    /// Creates a new indicator panel with the volume turned on
+    pub fn new<L: Into<Option<&'static str>>>(x: i32, y: i32, w: i32, h: i32, label: L) -> Self {
+        let mut flex = group::Flex::new(x, y, w, h, label).column();
+        let mut volume = button::CheckButton::default().with_label("Volume");
+        volume.set_checked(true);
+        flex.fixed(&volume, ROW_H);
+        let sma = study_row(&mut flex, "SMA", 20.);
+        let ema = study_row(&mut flex, "EMA", 50.);
+        let bollinger = study_row(&mut flex, "BB", 20.);
+        let mut row = group::Flex::default().row();
+        let label = frame::Frame::default()
+            .with_label("BB width")
+            .with_align(enums::Align::Left | enums::Align::Inside);
+        let mut bollinger_k = misc::Spinner::default();
+        bollinger_k.set_type(misc::SpinnerType::Float);
+        bollinger_k.set_range(0.5, 5.);
+        bollinger_k.set_step(0.5);
+        bollinger_k.set_value(2.);
+        row.end();
+        row.fixed(&label, 90);
+        flex.fixed(&row, ROW_H);
+        let rsi = study_row(&mut flex, "RSI", 14.);
+        flex.end();
+
+        let s = Self {
+            flex,
+            volume,
+            sma,
+            ema,
+            bollinger,
+            bollinger_k,
+            rsi,
+            cb: Rc::from(RefCell::from(None)),
+        };
+        let mut checks = [
+            s.volume.clone(),
+            s.sma.0.clone(),
+            s.ema.0.clone(),
+            s.bollinger.0.clone(),
+            s.rsi.0.clone(),
+        ];
+        for check in &mut checks {
+            let mut s = s.clone();
+            check.set_callback(move |_| s.changed());
+        }
+        let mut spinners = [
+            s.sma.1.clone(),
+            s.ema.1.clone(),
+            s.bollinger.1.clone(),
+            s.bollinger_k.clone(),
+            s.rsi.1.clone(),
+        ];
+        for spinner in &mut spinners {
+            let mut s = s.clone();
+            spinner.set_callback(move |_| s.changed());
+        }
+        s
+    }
+
+    /// Get the indicators which are turned on
+    pub fn indicators(&self) -> Indicators {
+        let period = |(on, spinner): &(button::CheckButton, misc::Spinner)| {
+            on.is_checked().then_some(spinner.value() as usize)
+        };
+        Indicators {
+            volume: self.volume.is_checked(),
+            sma: period(&self.sma),
+            ema: period(&self.ema),
+            bollinger: period(&self.bollinger).map(|p| (p, self.bollinger_k.value())),
+            rsi: period(&self.rsi),
+        }
+    }
+
+    /// Set a callback which is triggered when an indicator is turned on or off, or its settings change
+    pub fn set_callback<F: FnMut(&mut Self) + 'static>(&mut self, cb: F) {
+        *self.cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    fn changed(&mut self) {
+        if let Some(cb) = self.cb.clone().borrow_mut().as_mut() {
+            cb(self);
+        }
+    }
+}
+
+fltk::widget_extends!(IndicatorPanel, group::Flex, flex);
+
+// add a row with a check button and a spinner for the period of a study
+fn study_row(
+    flex: &mut group::Flex,
+    name: &str,
+    period: f64,
+) -> (button::CheckButton, misc::Spinner) {
+    let mut row = group::Flex::default().row();
+    let check = button::CheckButton::default().with_label(name);
+    let mut spinner = misc::Spinner::default();
+    spinner.set_type(misc::SpinnerType::Int);
+    spinner.set_range(2., 200.);
+    spinner.set_step(1.);
+    spinner.set_value(period);
+    row.end();
+    row.fixed(&check, 90);
+    flex.fixed(&row, ROW_H);
+    (check, spinner)
+}
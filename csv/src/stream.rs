@@ -0,0 +1,260 @@
+use crate::loader::{ColumnMap, Price, Rejected};
+use chrono::DateTime;
+use fltk::app;
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The candle intervals offered, in seconds, None keeping the rows as they're sent
+pub const INTERVALS: [(&str, Option<i64>); 7] = [
+    ("As sent", None),
+    ("1 minute", Some(60)),
+    ("5 minutes", Some(5 * 60)),
+    ("15 minutes", Some(15 * 60)),
+    ("1 hour", Some(60 * 60)),
+    ("4 hours", Some(4 * 60 * 60)),
+    ("1 day", Some(24 * 60 * 60)),
+];
+
+// how often a followed file is checked for new rows
+const POLL: Duration = Duration::from_millis(200);
+
+// the id of the next stream, so messages of a stopped stream can be told apart
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The kinds of source offered in the live dialog
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feed {
+    File,
+    Tcp,
+    Unix,
+}
+
+impl Feed {
+    /// The feeds in the order they're offered
+    pub const ALL: [Feed; 3] = [Feed::File, Feed::Tcp, Feed::Unix];
+
+    /// The name shown in the live dialog
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feed::File => "File",
+            Feed::Tcp => "TCP",
+            Feed::Unix => "Unix socket",
+        }
+    }
+}
+
+/// Where the rows of a stream come from
+#[derive(Debug, Clone)]
+pub enum Source {
+    /// A CSV file which is read from its start, then followed as it grows like `tail -f`
+    File(PathBuf, u8, ColumnMap),
+    /// A local TCP feed, e.g. 127.0.0.1:9000, sending a header line then one row per line
+    Tcp(String),
+    /// A Unix socket feed, sending a header line then one row per line
+    Unix(PathBuf),
+}
+
+/// A message from the thread reading a stream
+#[derive(Debug, Clone)]
+pub enum Message {
+    /// A row to roll into the candles, with its line number
+    Row(u64, u64, Price),
+    /// A row which couldn't be read
+    Rejected(u64, Rejected),
+    /// The feed ended, with the error which ended it if any
+    Closed(u64, Option<String>),
+}
+
+/// Reads the rows of a source on a thread, sending them to the app's channel until stopped
+pub struct Stream {
+    id: u64,
+    stop: Arc<AtomicBool>,
+    interval: Option<i64>,
+}
+
+impl Stream {
+    /// Opens the source and starts reading it, rows are rolled up into candles of the interval in seconds
+    pub fn start(
+        source: Source,
+        interval: Option<i64>,
+        sender: app::Sender<Message>,
+    ) -> std::io::Result<Self> {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let stop = Arc::new(AtomicBool::new(false));
+        // connect here so failures are reported right away
+        let (reader, delimiter, map, tail): (Box<dyn Read + Send>, _, _, _) = match source {
+            Source::File(path, delimiter, map) => (
+                Box::new(std::fs::File::open(path)?),
+                delimiter,
+                Some(map),
+                true,
+            ),
+            Source::Tcp(addr) => {
+                let sock = std::net::TcpStream::connect(addr)?;
+                sock.set_read_timeout(Some(POLL))?;
+                (Box::new(sock), b',', None, false)
+            }
+            #[cfg(unix)]
+            Source::Unix(path) => {
+                let sock = std::os::unix::net::UnixStream::connect(path)?;
+                sock.set_read_timeout(Some(POLL))?;
+                (Box::new(sock), b',', None, false)
+            }
+            #[cfg(not(unix))]
+            Source::Unix(_) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "Unix sockets aren't supported on this platform",
+                ))
+            }
+        };
+        let follow = Follow {
+            inner: reader,
+            stop: stop.clone(),
+            tail,
+        };
+        std::thread::spawn({
+            let stop = stop.clone();
+            move || {
+                let res = read_rows(follow, delimiter, map, id, &stop, &sender);
+                if !stop.load(Ordering::Relaxed) {
+                    sender.send(Message::Closed(id, res.err()));
+                }
+            }
+        });
+        Ok(Self { id, stop, interval })
+    }
+
+    /// Whether a message was sent by this stream rather than one which was stopped
+    pub fn sent(&self, msg: &Message) -> bool {
+        match msg {
+            Message::Row(id, _, _) | Message::Rejected(id, _) | Message::Closed(id, _) => {
+                *id == self.id
+            }
+        }
+    }
+
+    /// The interval the rows are rolled up into, in seconds
+    pub fn interval(&self) -> Option<i64> {
+        self.interval
+    }
+
+    /// Stops reading, no more messages are sent
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Adds a row to the candles, merging it into the last candle when both fall in the same interval.
+/// Rows older than the last candle are rejected
+pub fn roll_up(prices: &mut Vec<Price>, row: Price, interval: Option<i64>) -> Result<(), String> {
+    let start = match interval {
+        Some(secs) => {
+            let t = row.date.and_utc().timestamp();
+            DateTime::from_timestamp(t - t.rem_euclid(secs), 0).map_or(row.date, |d| d.naive_utc())
+        }
+        None => row.date,
+    };
+    match prices.last_mut() {
+        Some(last) if interval.is_some() && last.date == start => {
+            last.high = last.high.max(row.high);
+            last.low = last.low.min(row.low);
+            last.close = row.close;
+            last.volume += row.volume;
+        }
+        Some(last) if start < last.date => {
+            return Err(format!("{} is older than the last candle", row.date));
+        }
+        _ => prices.push(Price { date: start, ..row }),
+    }
+    Ok(())
+}
+
+// read the rows of a feed until it ends or the stream is stopped.
+// without a column map the columns are detected from the header line
+fn read_rows<R: Read>(
+    reader: R,
+    delimiter: u8,
+    map: Option<ColumnMap>,
+    id: u64,
+    stop: &AtomicBool,
+    sender: &app::Sender<Message>,
+) -> Result<(), String> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(reader);
+    let map = match map {
+        Some(map) => map,
+        None => {
+            let headers = rdr.headers().map_err(|e| e.to_string())?;
+            let map = ColumnMap::detect(headers).with_ticks();
+            if !map.is_complete() {
+                return Err(format!("the header {:?} lacks a date or a price", headers));
+            }
+            map
+        }
+    };
+    for result in rdr.records() {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        match result {
+            Ok(record) => {
+                let line = record.position().map_or(0, |p| p.line());
+                sender.send(match map.parse(&record, delimiter) {
+                    Ok(price) => Message::Row(id, line, price),
+                    Err(reason) => Message::Rejected(id, Rejected { line, reason }),
+                });
+            }
+            Err(e) if e.is_io_error() => return Err(e.to_string()),
+            Err(e) => {
+                let line = e.position().map_or(0, |p| p.line());
+                sender.send(Message::Rejected(
+                    id,
+                    Rejected {
+                        line,
+                        reason: e.to_string(),
+                    },
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// a reader which waits for more data instead of ending, until the stream is stopped.
+// files are polled at their end, sockets end when they're closed
+struct Follow<R> {
+    inner: R,
+    stop: Arc<AtomicBool>,
+    tail: bool,
+}
+
+impl<R: Read> Read for Follow<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            if self.stop.load(Ordering::Relaxed) {
+                return Ok(0);
+            }
+            match self.inner.read(buf) {
+                Ok(0) if self.tail => std::thread::sleep(POLL),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) => {}
+                res => return res,
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use serde_json::{Map, Value};
+use std::path::Path;
+
+/// A soloud filter of the effects chain, applied to everything which is played
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    Equalizer,
+    Echo,
+    Reverb,
+    Lofi,
+    BassBoost,
+}
+
+/// A parameter of an effect, its range and the value soloud starts with
+#[derive(Debug, Clone, Copy)]
+pub struct Param {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+}
+
+const fn param(name: &'static str, min: f32, max: f32, default: f32) -> Param {
+    Param {
+        name,
+        min,
+        max,
+        default,
+    }
+}
+
+// the parameters are in the order of the attributes of the filters, the wet/dry mix first
+const EQUALIZER: &[Param] = &[
+    param("Mix", 0., 1., 1.),
+    param("Band 1", 0., 4., 1.),
+    param("Band 2", 0., 4., 1.),
+    param("Band 3", 0., 4., 1.),
+    param("Band 4", 0., 4., 1.),
+    param("Band 5", 0., 4., 1.),
+    param("Band 6", 0., 4., 1.),
+    param("Band 7", 0., 4., 1.),
+    param("Band 8", 0., 4., 1.),
+];
+const ECHO: &[Param] = &[
+    param("Mix", 0., 1., 1.),
+    param("Delay", 0.01, 1., 0.3),
+    param("Decay", 0., 1., 0.7),
+    param("Filter", 0., 1., 0.),
+];
+const REVERB: &[Param] = &[
+    param("Mix", 0., 1., 1.),
+    param("Freeze", 0., 1., 0.),
+    param("Room size", 0., 1., 0.5),
+    param("Damping", 0., 1., 0.5),
+    param("Width", 0., 1., 1.),
+];
+const LOFI: &[Param] = &[
+    param("Mix", 0., 1., 1.),
+    param("Sample rate", 100., 22000., 4000.),
+    param("Bit depth", 0.5, 16., 3.),
+];
+const BASS_BOOST: &[Param] = &[param("Mix", 0., 1., 1.), param("Boost", 0., 10., 2.)];
+
+impl Effect {
+    /// The effects in the order of the chain
+    pub const ALL: [Effect; 5] = [
+        Effect::Equalizer,
+        Effect::Echo,
+        Effect::Reverb,
+        Effect::Lofi,
+        Effect::BassBoost,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Equalizer => "Equalizer",
+            Effect::Echo => "Echo",
+            Effect::Reverb => "Reverb",
+            Effect::Lofi => "Lo-fi",
+            Effect::BassBoost => "Bass boost",
+        }
+    }
+
+    pub fn params(self) -> &'static [Param] {
+        match self {
+            Effect::Equalizer => EQUALIZER,
+            Effect::Echo => ECHO,
+            Effect::Reverb => REVERB,
+            Effect::Lofi => LOFI,
+            Effect::BassBoost => BASS_BOOST,
+        }
+    }
+}
+
+/// Whether an effect is bypassed, and the values of its parameters
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub bypass: bool,
+    pub values: Vec<f32>,
+}
+
+impl Settings {
+    // bypassed, the sound being unchanged until an effect is turned on
+    fn new(effect: Effect) -> Self {
+        Self {
+            bypass: true,
+            values: effect.params().iter().map(|p| p.default).collect(),
+        }
+    }
+}
+
+/// The settings of every effect, which are saved to and loaded from JSON files
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preset {
+    settings: Vec<Settings>,
+}
+
+impl Default for Preset {
+    fn default() -> Self {
+        Self {
+            settings: Effect::ALL.iter().map(|&e| Settings::new(e)).collect(),
+        }
+    }
+}
+
+impl Preset {
+    pub fn get(&self, effect: Effect) -> &Settings {
+        &self.settings[effect as usize]
+    }
+
+    pub fn get_mut(&mut self, effect: Effect) -> &mut Settings {
+        &mut self.settings[effect as usize]
+    }
+
+    /// Reads a preset from a JSON file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    /// Parses an object with an object per effect named after it, holding `bypass` and the
+    /// parameters by name. Missing effects and parameters keep their defaults
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let json: Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+        if !json.is_object() {
+            return Err("Not an effects preset".to_string());
+        }
+        let mut preset = Self::default();
+        for effect in Effect::ALL {
+            let Some(obj) = json[effect.name()].as_object() else {
+                continue;
+            };
+            let settings = preset.get_mut(effect);
+            if let Some(bypass) = obj.get("bypass").and_then(Value::as_bool) {
+                settings.bypass = bypass;
+            }
+            for (value, p) in settings.values.iter_mut().zip(effect.params()) {
+                if let Some(v) = obj.get(p.name).and_then(Value::as_f64) {
+                    *value = (v as f32).clamp(p.min, p.max);
+                }
+            }
+        }
+        Ok(preset)
+    }
+
+    /// Writes the preset to a JSON file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let mut json = Map::new();
+        for effect in Effect::ALL {
+            let settings = self.get(effect);
+            let mut obj = Map::new();
+            obj.insert("bypass".to_string(), settings.bypass.into());
+            for (value, p) in settings.values.iter().zip(effect.params()) {
+                // rounded, as f32 values are long once widened
+                let value = (*value as f64 * 1000.).round() / 1000.;
+                obj.insert(p.name.to_string(), value.into());
+            }
+            json.insert(effect.name().to_string(), obj.into());
+        }
+        let text = serde_json::to_string_pretty(&json).map_err(|e| e.to_string())?;
+        std::fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
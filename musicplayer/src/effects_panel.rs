@@ -0,0 +1,257 @@
+use crate::effects::{Effect, Param, Preset, Settings};
+use crate::fancy_slider::FancySlider;
+use fltk::{enums::*, prelude::*, *};
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+const BUTTON_H: i32 = 25;
+const TAB_H: i32 = 25;
+// the height of a parameter's row
+const ROW_H: i32 = 38;
+// the length of the sliders of the equalizer's bands
+const BAND_H: i32 = 280;
+const NAME_W: i32 = 90;
+const VALUE_W: i32 = 55;
+
+type Callback = Rc<RefCell<Option<Box<dyn FnMut(&mut EffectsPanel, Effect)>>>>;
+
+// the controls of a parameter
+#[derive(Clone)]
+struct Control {
+    slider: FancySlider,
+    value: frame::Frame,
+}
+
+impl Control {
+    // a row with the name, the slider and the value, or a column with the value above
+    // a vertical slider of the given length over the name
+    fn new(p: &Param, (x, y, w, h): (i32, i32, i32, i32), len: i32, vertical: bool) -> Self {
+        let (mut name, mut slider, mut value) = if vertical {
+            let value = frame::Frame::new(x, y, w, h, "");
+            let mut slider = FancySlider::new(x + (w - h) / 2, y + h, h, len);
+            slider.set_vertical(true);
+            let name = frame::Frame::new(x, y + h + len, w, h, p.name);
+            (name, slider, value)
+        } else {
+            let mut name = frame::Frame::new(x, y, len, h, p.name);
+            name.set_align(Align::Left | Align::Inside);
+            let slider = FancySlider::new(x + len, y, w - len - VALUE_W, h);
+            let mut value = frame::Frame::new(x + w - VALUE_W, y, VALUE_W, h, "");
+            value.set_align(Align::Right | Align::Inside);
+            (name, slider, value)
+        };
+        slider.set_bounds(p.min as f64, p.max as f64);
+        slider.set_knob_size(16);
+        slider.set_knob_color(Color::from_u32(0x868db1));
+        slider.set_color(Color::from_u32(0x2c2c38));
+        for label in [&mut name, &mut value] {
+            label.set_label_color(Color::White);
+            label.set_label_size(12);
+        }
+        Self { slider, value }
+    }
+}
+
+/// A tab per effect with a bypass toggle and a slider per parameter,
+/// over buttons to load, save and reset the preset
+#[derive(Clone)]
+pub struct EffectsPanel {
+    grp: group::Group,
+    preset: Rc<RefCell<Preset>>,
+    // indexed by effect
+    bypass: Vec<button::CheckButton>,
+    controls: Vec<Vec<Control>>,
+    cb: Callback,
+}
+
+impl EffectsPanel {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        let grp = group::Group::new(x, y, w, h, "");
+        let mut tabs = group::Tabs::new(x, y, w, h - BUTTON_H, "");
+        tabs.set_color(Color::from_u32(0x1c1c24));
+        tabs.set_selection_color(Color::from_u32(0x868db1));
+        let mut bypass = vec![];
+        let mut controls = vec![];
+        for effect in Effect::ALL {
+            let top = y + TAB_H;
+            let mut tab = group::Group::new(x, top, w, h - BUTTON_H - TAB_H, effect.name());
+            tab.set_frame(FrameType::FlatBox);
+            tab.set_color(Color::from_u32(0x1c1c24));
+            tab.set_label_color(Color::White);
+            tab.set_label_size(12);
+            let mut check = button::CheckButton::new(x + 10, top + 5, w - 20, BUTTON_H, "Bypass");
+            check.set_label_color(Color::White);
+            bypass.push(check);
+            let rows = effect
+                .params()
+                .iter()
+                .enumerate()
+                .map(|(i, p)| {
+                    // the bands of the equalizer stand side by side under its mix
+                    if effect == Effect::Equalizer && i > 0 {
+                        let col_w = (w - 20) / (effect.params().len() as i32 - 1);
+                        let col_x = x + 10 + (i as i32 - 1) * col_w;
+                        Control::new(p, (col_x, top + 75, col_w, 20), BAND_H, true)
+                    } else {
+                        let row_y = top + 40 + i as i32 * ROW_H;
+                        Control::new(p, (x + 10, row_y, w - 20, 20), NAME_W, false)
+                    }
+                })
+                .collect();
+            controls.push(rows);
+            tab.end();
+        }
+        tabs.end();
+        let labels = ["Load...", "Save...", "Reset"];
+        let bw = w / labels.len() as i32;
+        let mut buttons: Vec<button::Button> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                button::Button::new(x + i as i32 * bw, y + h - BUTTON_H, bw, BUTTON_H, *label)
+            })
+            .collect();
+        grp.end();
+
+        let mut s = Self {
+            grp,
+            preset: Rc::default(),
+            bypass,
+            controls,
+            cb: Rc::from(RefCell::from(None)),
+        };
+        s.show_preset();
+
+        for effect in Effect::ALL {
+            s.bypass[effect as usize].clone().set_callback({
+                let mut s = s.clone();
+                move |b| {
+                    s.preset.borrow_mut().get_mut(effect).bypass = b.is_checked();
+                    s.changed(effect);
+                }
+            });
+            for (i, p) in effect.params().iter().enumerate() {
+                s.controls[effect as usize][i].slider.clone().set_callback({
+                    let mut s = s.clone();
+                    let p = *p;
+                    move |slider| {
+                        let value = slider.value() as f32;
+                        s.preset.borrow_mut().get_mut(effect).values[i] = value;
+                        let label = &mut s.controls[effect as usize][i].value;
+                        label.set_label(&format_value(&p, value));
+                        label.redraw();
+                        s.changed(effect);
+                    }
+                });
+            }
+        }
+        buttons[0].set_callback({
+            let mut s = s.clone();
+            move |_| {
+                let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseFile);
+                dlg.set_filter("Presets\t*.json");
+                dlg.show();
+                let path = dlg.filename();
+                if path.as_os_str().is_empty() {
+                    return;
+                }
+                match Preset::load(&path) {
+                    Ok(preset) => s.set_preset(preset),
+                    Err(e) => {
+                        dialog::alert_default(&format!("Couldn't load {}: {}", path.display(), e))
+                    }
+                }
+            }
+        });
+        buttons[1].set_callback({
+            let s = s.clone();
+            move |_| {
+                let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseSaveFile);
+                dlg.set_option(dialog::FileDialogOptions::SaveAsConfirm);
+                dlg.set_filter("Presets\t*.json");
+                dlg.show();
+                let path = dlg.filename();
+                if path.as_os_str().is_empty() {
+                    return;
+                }
+                if let Err(e) = s.preset.borrow().save(&path) {
+                    dialog::alert_default(&format!("Couldn't save {}: {}", path.display(), e));
+                }
+            }
+        });
+        buttons[2].set_callback({
+            let mut s = s.clone();
+            move |_| s.set_preset(Preset::default())
+        });
+        s
+    }
+
+    /// The settings of an effect as set by the user
+    pub fn settings(&self, effect: Effect) -> Settings {
+        self.preset.borrow().get(effect).clone()
+    }
+
+    /// Set a callback which is triggered when the settings of an effect change
+    pub fn set_callback<F: FnMut(&mut Self, Effect) + 'static>(&mut self, cb: F) {
+        *self.cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    // replace every setting, e.g. with a loaded preset
+    fn set_preset(&mut self, preset: Preset) {
+        *self.preset.borrow_mut() = preset;
+        self.show_preset();
+        for effect in Effect::ALL {
+            self.changed(effect);
+        }
+    }
+
+    // move the controls to the settings of the preset
+    fn show_preset(&mut self) {
+        let preset = self.preset.borrow();
+        for effect in Effect::ALL {
+            let settings = preset.get(effect);
+            self.bypass[effect as usize].set_checked(settings.bypass);
+            let controls = &mut self.controls[effect as usize];
+            for ((control, p), &value) in controls
+                .iter_mut()
+                .zip(effect.params())
+                .zip(&settings.values)
+            {
+                control.slider.set_value(value as f64);
+                control.value.set_label(&format_value(p, value));
+            }
+        }
+        drop(preset);
+        self.grp.redraw();
+    }
+
+    fn changed(&mut self, effect: Effect) {
+        if let Some(cb) = self.cb.clone().borrow_mut().as_mut() {
+            cb(self, effect);
+        }
+    }
+}
+
+// whole numbers for the parameters with large ranges, like the sample rate
+fn format_value(p: &Param, value: f32) -> String {
+    if p.max >= 100. {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+impl Deref for EffectsPanel {
+    type Target = group::Group;
+
+    fn deref(&self) -> &Self::Target {
+        &self.grp
+    }
+}
+
+impl DerefMut for EffectsPanel {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.grp
+    }
+}
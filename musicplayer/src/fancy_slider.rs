@@ -1,31 +1,243 @@
 use fltk::{enums::*, prelude::*, *};
+use std::cell::Cell;
 use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
 
+// the thickness of the track the knob moves along
+const TRACK: i32 = 10;
+// the steps of the range the keyboard and the wheel move by, when the slider has no step
+const STEPS: f64 = 100.;
+
+#[derive(Debug, Clone, Copy)]
+struct Style {
+    knob_size: i32,
+    knob_color: Color,
+    vertical: bool,
+}
+
+/// A slider with a round knob over a track filled up to it.
+/// Its value is within the bounds set with `set_bounds` and changes by dragging,
+/// by the arrow, Home and End keys and by the mouse wheel, each change triggering its callback.
+/// The track takes the color of the slider and its filled part the selection color
+#[derive(Clone)]
 pub struct FancySlider {
     s: valuator::Slider,
+    style: Rc<Cell<Style>>,
 }
 
 impl FancySlider {
-    pub fn new(x: i32, y: i32) -> Self {
-        let mut s = valuator::Slider::new(x, y, 300, 10, "");
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        let mut s = valuator::Slider::new(x, y, w, h, "");
         s.set_type(valuator::SliderType::Horizontal);
-        s.set_frame(FrameType::RFlatBox);
+        // the slider draws nothing itself, everything being drawn by draw_slider
+        s.set_frame(FrameType::NoBox);
+        s.set_slider_frame(FrameType::NoBox);
         s.set_color(Color::from_u32(0x868db1));
-        s.draw(|s| {
-            draw::set_draw_color(Color::Blue);
-            draw::draw_pie(
-                s.x() - 10 + (s.w() as f64 * s.value()) as i32,
-                s.y() - 10,
-                30,
-                30,
-                0.,
-                360.,
-            );
+        s.set_selection_color(Color::Blue);
+        let style = Rc::from(Cell::from(Style {
+            knob_size: 30,
+            knob_color: Color::Blue,
+            vertical: false,
+        }));
+        s.draw({
+            let style = style.clone();
+            move |s| draw_slider(s, style.get())
         });
-        Self { s }
+        s.handle({
+            let style = style.clone();
+            move |s, ev| match ev {
+                Event::Push => {
+                    s.take_focus().ok();
+                    let (x, y) = app::event_coords();
+                    move_to(s, style.get(), x, y);
+                    true
+                }
+                Event::Drag => {
+                    let (x, y) = app::event_coords();
+                    move_to(s, style.get(), x, y);
+                    true
+                }
+                Event::Released => true,
+                Event::MouseWheel => match app::event_dy() {
+                    app::MouseWheel::Up => step_by(s, 1.),
+                    app::MouseWheel::Down => step_by(s, -1.),
+                    _ => false,
+                },
+                Event::KeyDown => match app::event_key() {
+                    Key::Right | Key::Up => step_by(s, 1.),
+                    Key::Left | Key::Down => step_by(s, -1.),
+                    Key::Home => set(s, s.minimum()),
+                    Key::End => set(s, s.maximum()),
+                    _ => false,
+                },
+                Event::Focus | Event::Unfocus => {
+                    s.redraw();
+                    true
+                }
+                // receive the wheel while the mouse is over the slider
+                Event::Enter | Event::Leave => true,
+                _ => false,
+            }
+        });
+        Self { s, style }
+    }
+
+    /// Sets the diameter of the knob, which is drawn within the slider
+    pub fn set_knob_size(&mut self, size: i32) {
+        self.update_style(|style| style.knob_size = size);
+    }
+
+    pub fn set_knob_color(&mut self, color: Color) {
+        self.update_style(|style| style.knob_color = color);
+    }
+
+    /// Makes the knob move up and down, the minimum being at the bottom
+    pub fn set_vertical(&mut self, vertical: bool) {
+        self.update_style(|style| style.vertical = vertical);
+    }
+
+    fn update_style(&mut self, f: impl FnOnce(&mut Style)) {
+        let mut style = self.style.get();
+        f(&mut style);
+        self.style.set(style);
+        self.s.redraw();
     }
 }
 
+// the knob fits across the slider
+fn knob_size(s: &valuator::Slider, style: Style) -> i32 {
+    style
+        .knob_size
+        .min(if style.vertical { s.w() } else { s.h() })
+}
+
+// the place of the knob's center along the track, from its start to its end
+fn track(s: &valuator::Slider, style: Style) -> (i32, i32) {
+    let knob = knob_size(s, style);
+    if style.vertical {
+        (s.y() + s.h() - knob / 2, s.y() + knob / 2)
+    } else {
+        (s.x() + knob / 2, s.x() + s.w() - knob / 2)
+    }
+}
+
+// how far the value is between the minimum and the maximum, from 0 to 1
+fn fraction(s: &valuator::Slider) -> f64 {
+    let range = s.maximum() - s.minimum();
+    if range == 0. {
+        0.
+    } else {
+        ((s.value() - s.minimum()) / range).clamp(0., 1.)
+    }
+}
+
+fn draw_slider(s: &mut valuator::Slider, style: Style) {
+    // a slider without a box doesn't erase the knob from where it was
+    let bg = s.parent().map_or(Color::Background, |p| p.color());
+    draw::draw_rect_fill(s.x(), s.y(), s.w(), s.h(), bg);
+    let knob = knob_size(s, style);
+    let (start, end) = track(s, style);
+    let pos = start + ((end - start) as f64 * fraction(s)).round() as i32;
+    if style.vertical {
+        let x = s.x() + (s.w() - TRACK) / 2;
+        draw::draw_box(
+            FrameType::RFlatBox,
+            x,
+            end - TRACK / 2,
+            TRACK,
+            start - end + TRACK,
+            s.color(),
+        );
+        draw::draw_box(
+            FrameType::RFlatBox,
+            x,
+            pos,
+            TRACK,
+            start - pos + TRACK / 2,
+            s.selection_color(),
+        );
+        draw_knob(s, style, s.x() + (s.w() - knob) / 2, pos - knob / 2, knob);
+    } else {
+        let y = s.y() + (s.h() - TRACK) / 2;
+        draw::draw_box(
+            FrameType::RFlatBox,
+            start - TRACK / 2,
+            y,
+            end - start + TRACK,
+            TRACK,
+            s.color(),
+        );
+        draw::draw_box(
+            FrameType::RFlatBox,
+            start - TRACK / 2,
+            y,
+            pos - start + TRACK / 2,
+            TRACK,
+            s.selection_color(),
+        );
+        draw_knob(s, style, pos - knob / 2, s.y() + (s.h() - knob) / 2, knob);
+    }
+}
+
+// a ring around the knob shows which slider the keys move
+fn draw_knob(s: &valuator::Slider, style: Style, x: i32, y: i32, size: i32) {
+    draw::set_draw_color(style.knob_color);
+    draw::draw_pie(x, y, size, size, 0., 360.);
+    if s.has_focus() {
+        draw::set_draw_color(Color::White);
+        draw::draw_arc(x, y, size, size, 0., 360.);
+    }
+}
+
+// move the knob under the mouse, keeping it on the track
+fn move_to(s: &mut valuator::Slider, style: Style, x: i32, y: i32) {
+    let (start, end) = track(s, style);
+    let pos = if style.vertical { y } else { x };
+    let t = if start == end {
+        0.
+    } else {
+        ((pos - start) as f64 / (end - start) as f64).clamp(0., 1.)
+    };
+    let value = s.minimum() + t * (s.maximum() - s.minimum());
+    set(s, round(s, value));
+}
+
+// move the value by steps towards the maximum, or towards the minimum when negative
+fn step_by(s: &mut valuator::Slider, steps: f64) -> bool {
+    let step = if s.step() > 0. {
+        s.step()
+    } else {
+        (s.maximum() - s.minimum()).abs() / STEPS
+    };
+    let step = if s.maximum() < s.minimum() {
+        -step
+    } else {
+        step
+    };
+    set(s, round(s, s.value() + steps * step))
+}
+
+fn round(s: &valuator::Slider, value: f64) -> f64 {
+    let step = s.step();
+    if step > 0. {
+        (value / step).round() * step
+    } else {
+        value
+    }
+}
+
+// set a value within the bounds, triggering the callback when it changes
+fn set(s: &mut valuator::Slider, value: f64) -> bool {
+    let (min, max) = (s.minimum(), s.maximum());
+    let value = value.clamp(min.min(max), min.max(max));
+    if value != s.value() {
+        s.set_value(value);
+        s.redraw();
+        s.do_callback();
+    }
+    true
+}
+
 impl Deref for FancySlider {
     type Target = valuator::Slider;
 
@@ -38,4 +250,4 @@ impl DerefMut for FancySlider {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.s
     }
-}
\ No newline at end of file
+}
@@ -1,5 +1,7 @@
 use fltk::{
     app,
+    button::*,
+    dialog,
     enums::*,
     frame::*,
     prelude::*,
@@ -15,21 +17,65 @@ use power_button::PowerButton;
 mod fancy_slider;
 use fancy_slider::FancySlider;
 
+mod effects;
+
+mod effects_panel;
+use effects_panel::EffectsPanel;
+
+mod player;
+use player::{Player, State};
+
+mod playlist;
+use playlist::Playlist;
+
+mod playlist_panel;
+use playlist_panel::PlaylistPanel;
+
+mod transport;
+use transport::{Action, Transport};
+
+mod visualizer;
+use visualizer::Visualizer;
+
+// the track in the playlist at startup
 const TRACK: &str = "Alarm.mp3";
+// how often the position of the playback is shown, in seconds
+const POSITION_UPDATE: f64 = 0.1;
+// how often the visualization is drawn, in seconds
+const VISUALIZE_UPDATE: f64 = 1. / 30.;
 
 fn main() {
     let app = app::App::default();
     let mut wind = DoubleWindow::default()
-        .with_size(400, 300)
+        .with_size(1040, 500)
         .center_screen()
         .with_label("Music Player");
     wind.make_resizable(true);
     
-    let mut frm = Frame::new(160, 80, 80, 40, TRACK);
-    frm.set_label_size(20);
+    let mut playlist = Playlist::default();
+    playlist.add(TRACK);
+    let playlist = Rc::from(RefCell::from(playlist));
+
+    let mut frm = Frame::new(0, 30, 400, 100, "");
+    frm.set_label_size(16);
     frm.set_label_color(Color::White);
-    let mut slider = FancySlider::new(50, 150);
+    show_current(&mut frm, &playlist.borrow());
+    let mut slider = FancySlider::new(35, 135, 330, 30);
+    // soloud plays at full volume to begin with
+    slider.set_bounds(0., 1.);
+    slider.set_value(1.);
     let mut but = PowerButton::new(160, 210);
+    let mut prev = Button::new(90, 230, 40, 40, "@|<");
+    let mut next = Button::new(270, 230, 40, 40, "@>|");
+    for b in [&mut prev, &mut next] {
+        b.set_frame(FrameType::FlatBox);
+        b.set_color(Color::Black);
+        b.set_label_color(Color::White);
+    }
+    let mut transport = Transport::new(20, 305);
+    let mut visualizer = Visualizer::new(20, 375, 360, 115);
+    let mut panel = PlaylistPanel::new(400, 0, 320, 500, playlist.clone());
+    let mut effects = EffectsPanel::new(720, 0, 320, 500);
 
     let sl = Soloud::default().unwrap();
 
@@ -37,49 +83,163 @@ fn main() {
     wind.end();
     wind.show();
 
-    let sl = Rc::from(RefCell::from(sl));
+    let player = Rc::from(RefCell::from(Player::new(sl)));
+
+    effects.set_callback({
+        let player = player.clone();
+        move |p, effect| player.borrow_mut().set_effect(effect, &p.settings(effect))
+    });
 
+    // turning the power on plays the current track, turning it off stops it
+    let power = but.clone();
     but.set_callback({
-        let sl = sl.clone();
+        let player = player.clone();
+        let playlist = playlist.clone();
+        let mut power = power.clone();
         move |_| {
-            if sl.borrow().active_voice_count() > 0 {
-                // Checks that no active audio is playing
-                sl.borrow().stop_all();
-                return;
+            if power.is_on() {
+                play_current(&player, &playlist, &mut power);
+            } else {
+                player.borrow_mut().stop();
+            }
+        }
+    });
+
+    // show the new current track, which is played if a track was playing
+    let jump = {
+        let player = player.clone();
+        let playlist = playlist.clone();
+        let mut power = power.clone();
+        let mut frm = frm.clone();
+        let mut panel = panel.clone();
+        move || {
+            show_current(&mut frm, &playlist.borrow());
+            panel.refresh();
+            if player.borrow().state() != State::Stopped {
+                play_current(&player, &playlist, &mut power);
             }
-            let mut wav = audio::Wav::default();
-            wav.load(&std::path::Path::new(TRACK)).unwrap();
-            wav.set_looping(true);
-            sl.borrow().play(&wav);
-            while sl.borrow().active_voice_count() > 0 {
-                app.wait();
+        }
+    };
+    prev.set_callback({
+        let playlist = playlist.clone();
+        let mut jump = jump.clone();
+        move |_| {
+            playlist.borrow_mut().previous();
+            jump();
+        }
+    });
+    next.set_callback({
+        let playlist = playlist.clone();
+        let mut jump = jump.clone();
+        move |_| {
+            playlist.borrow_mut().next(false);
+            jump();
+        }
+    });
+    panel.set_callback({
+        let mut jump = jump.clone();
+        move |_| jump()
+    });
+
+    transport.set_callback({
+        let player = player.clone();
+        let playlist = playlist.clone();
+        let mut power = power.clone();
+        move |_, action| {
+            let state = player.borrow().state();
+            match (action, state) {
+                (Action::PlayPause, State::Stopped) => {
+                    play_current(&player, &playlist, &mut power)
+                }
+                (Action::PlayPause, _) => player.borrow_mut().toggle_pause(),
+                (Action::Stop, _) => {
+                    player.borrow_mut().stop();
+                    power.set_on(false);
+                }
+                (Action::Seek(pos), _) => player.borrow_mut().seek(pos),
             }
         }
     });
 
-    slider.handle({
-        let sl = sl.clone();
-        move |s, ev| match ev {
-            Event::Push => true,
-            Event::Drag => {
-                let slider_x = s.x() as f32 / 50.0;
-                let (x, _y) = app::event_coords();
-                if x > 45 && x < 350 {
-                    s.set_pos(x - 15, 150);
-                    sl.borrow_mut().set_global_volume(slider_x);
+    // follow the playback on the seek bar, moving on to the next track at the end of one
+    app::add_timeout3(POSITION_UPDATE, {
+        let player = player.clone();
+        let mut power = power.clone();
+        move |timer| {
+            let ended = player.borrow_mut().ended();
+            if ended {
+                if playlist.borrow_mut().next(true).is_some() {
+                    show_current(&mut frm, &playlist.borrow());
+                    panel.refresh();
+                    play_current(&player, &playlist, &mut power);
+                } else {
+                    power.set_on(false);
                 }
-                app::redraw();
-                true
             }
-            _ => false,
+            let player = player.borrow();
+            let (pos, length) = player.position();
+            transport.set_position(pos, length);
+            transport.set_playing(matches!(player.state(), State::Playing(_)));
+            app::repeat_timeout3(POSITION_UPDATE, timer);
+        }
+    });
+
+    // the spectrum and the waveform follow the playback, holding still while paused
+    app::add_timeout3(VISUALIZE_UPDATE, {
+        let player = player.clone();
+        move |timer| {
+            let player = player.borrow();
+            match player.state() {
+                State::Playing(_) => visualizer.update(&player.spectrum(), &player.waveform()),
+                State::Paused(_) => (),
+                State::Stopped => visualizer.clear(),
+            }
+            app::repeat_timeout3(VISUALIZE_UPDATE, timer);
         }
     });
 
+    slider.set_callback({
+        let player = player.clone();
+        move |s| player.borrow_mut().set_volume(s.value() as f32)
+    });
+
     wind.set_callback(move |_| {
         // Triggered when the window closes
-        sl.borrow().stop_all(); // Stop any playing audio before quitting
+        player.borrow_mut().stop(); // Stop any playing audio before quitting
         app.quit();
     });
 
     app.run().unwrap();
 }
+
+// play the current track, turning the power off when there's none or it can't be loaded
+fn play_current(
+    player: &RefCell<Player>,
+    playlist: &RefCell<Playlist>,
+    power: &mut PowerButton,
+) {
+    let Some(path) = playlist.borrow().current_track().map(|track| track.path.clone()) else {
+        player.borrow_mut().stop();
+        power.set_on(false);
+        return;
+    };
+    // not borrowed while the alert runs the event loop
+    let played = player.borrow_mut().play(&path);
+    match played {
+        Ok(()) => power.set_on(true),
+        Err(e) => {
+            power.set_on(false);
+            dialog::alert_default(&format!("Couldn't play {}: {}", path.display(), e));
+        }
+    }
+}
+
+// show the tags of the current track in place of its file name
+fn show_current(frm: &mut Frame, playlist: &Playlist) {
+    let label = playlist
+        .current_track()
+        .map(|track| track.description())
+        .unwrap_or_else(|| "No track".to_string());
+    // @ starts a symbol in labels
+    frm.set_label(&label.replace('@', "@@"));
+}
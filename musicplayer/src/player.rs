@@ -0,0 +1,156 @@
+use crate::effects::{Effect, Settings};
+use soloud::*;
+use std::path::Path;
+
+// the longest delay of the echo, its buffer being allocated for it
+const ECHO_MAX_DELAY: f32 = 1.;
+
+/// Where the playback of the track is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum State {
+    Stopped,
+    Playing(Handle),
+    Paused(Handle),
+}
+
+/// Plays a track at a time, the app polling it for the end of the track
+pub struct Player {
+    sl: Soloud,
+    // kept while its voice plays
+    wav: audio::Wav,
+    state: State,
+    // kept while they're in the chain, indexed by effect
+    eq: filter::EqFilter,
+    echo: filter::EchoFilter,
+    reverb: filter::FreeverbFilter,
+    lofi: filter::LofiFilter,
+    bass_boost: filter::BassboostFilter,
+    active: [bool; Effect::ALL.len()],
+}
+
+impl Player {
+    pub fn new(mut sl: Soloud) -> Self {
+        // keeps the spectrum and the wave of the output
+        sl.set_visualize_enable(true);
+        let mut echo = filter::EchoFilter::default();
+        echo.set_params(ECHO_MAX_DELAY).ok();
+        Self {
+            sl,
+            wav: audio::Wav::default(),
+            state: State::Stopped,
+            eq: filter::EqFilter::default(),
+            echo,
+            reverb: filter::FreeverbFilter::default(),
+            lofi: filter::LofiFilter::default(),
+            bass_boost: filter::BassboostFilter::default(),
+            active: [false; Effect::ALL.len()],
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+
+    /// Loads a file and plays it from its start, in place of the track which was playing
+    pub fn play<P: AsRef<Path>>(&mut self, path: P) -> Result<(), SoloudError> {
+        self.stop();
+        let mut wav = audio::Wav::default();
+        wav.load(path.as_ref())?;
+        self.wav = wav;
+        self.state = State::Playing(self.sl.play(&self.wav));
+        Ok(())
+    }
+
+    /// Pauses a playing track, or resumes a paused one
+    pub fn toggle_pause(&mut self) {
+        self.state = match self.state {
+            State::Playing(handle) => {
+                self.sl.set_pause(handle, true);
+                State::Paused(handle)
+            }
+            State::Paused(handle) => {
+                self.sl.set_pause(handle, false);
+                State::Playing(handle)
+            }
+            State::Stopped => State::Stopped,
+        };
+    }
+
+    pub fn stop(&mut self) {
+        if let State::Playing(handle) | State::Paused(handle) = self.state {
+            self.sl.stop(handle);
+        }
+        self.state = State::Stopped;
+    }
+
+    /// Jumps to a position in seconds
+    pub fn seek(&mut self, pos: f64) {
+        if let State::Playing(handle) | State::Paused(handle) = self.state {
+            self.sl.seek(handle, pos).ok();
+        }
+    }
+
+    /// The position in the track and its length, in seconds
+    pub fn position(&self) -> (f64, f64) {
+        match self.state {
+            State::Playing(handle) | State::Paused(handle) => {
+                (self.sl.stream_position(handle), self.wav.length())
+            }
+            State::Stopped => (0., self.wav.length()),
+        }
+    }
+
+    /// Whether the track played to its end since the last call, the player being stopped then
+    pub fn ended(&mut self) -> bool {
+        match self.state {
+            State::Playing(handle) if !self.sl.is_valid_voice_handle(handle) => {
+                self.state = State::Stopped;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The magnitudes of the frequencies of the output, from the lowest
+    pub fn spectrum(&self) -> Vec<f32> {
+        self.sl.calc_fft()
+    }
+
+    /// The latest samples of the output
+    pub fn waveform(&self) -> Vec<f32> {
+        self.sl.wave()
+    }
+
+    /// Puts an effect in the chain with the values of its parameters,
+    /// or takes it out when it's bypassed
+    pub fn set_effect(&mut self, effect: Effect, settings: &Settings) {
+        let id = effect as u32;
+        let active = &mut self.active[effect as usize];
+        if settings.bypass {
+            if *active {
+                self.sl.set_global_filter(id, None::<&filter::EqFilter>);
+                *active = false;
+            }
+            return;
+        }
+        if !*active {
+            match effect {
+                Effect::Equalizer => self.sl.set_global_filter(id, Some(&self.eq)),
+                Effect::Echo => self.sl.set_global_filter(id, Some(&self.echo)),
+                Effect::Reverb => self.sl.set_global_filter(id, Some(&self.reverb)),
+                Effect::Lofi => self.sl.set_global_filter(id, Some(&self.lofi)),
+                Effect::BassBoost => self.sl.set_global_filter(id, Some(&self.bass_boost)),
+            }
+            *active = true;
+        }
+        // the attributes of a filter are numbered in the order of its parameters
+        for (attr, &value) in settings.values.iter().enumerate() {
+            self.sl
+                .set_filter_parameter(Handle::PRIMARY, id, attr as u32, value);
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.sl.set_global_volume(volume);
+    }
+}
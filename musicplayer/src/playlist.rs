@@ -0,0 +1,332 @@
+use rand::seq::SliceRandom;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use symphonia::core::{
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::{MetadataOptions, StandardTagKey},
+    probe::Hint,
+};
+
+// the files added with a folder
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac"];
+
+/// What happens at the end of a track
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Repeat {
+    /// Stop after the last track
+    Off,
+    /// Go back to the first track after the last one
+    All,
+    /// Play the same track again
+    One,
+}
+
+/// An audio file with the tags found in it
+#[derive(Debug, Clone)]
+pub struct Track {
+    pub path: PathBuf,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// In seconds
+    pub duration: Option<f64>,
+}
+
+impl Track {
+    /// Reads the tags of an audio file, a file without tags only has its path
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        let mut track = Self {
+            path: path.as_ref().to_path_buf(),
+            title: None,
+            artist: None,
+            album: None,
+            duration: None,
+        };
+        track.read_tags();
+        track
+    }
+
+    // fill the tags from the container, e.g. ID3, and from the format, e.g. Vorbis comments
+    fn read_tags(&mut self) {
+        let Ok(file) = File::open(&self.path) else {
+            return;
+        };
+        let mut hint = Hint::new();
+        if let Some(ext) = self.path.extension().and_then(|e| e.to_str()) {
+            hint.with_extension(ext);
+        }
+        let mss = MediaSourceStream::new(Box::new(file), Default::default());
+        let Ok(mut probed) = symphonia::default::get_probe().format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        ) else {
+            return;
+        };
+        let mut tags = vec![];
+        if let Some(rev) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+            tags.extend(rev.tags().iter().cloned());
+        }
+        if let Some(rev) = probed.format.metadata().current() {
+            tags.extend(rev.tags().iter().cloned());
+        }
+        for tag in tags {
+            let value = Some(tag.value.to_string()).filter(|v| !v.trim().is_empty());
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => self.title = value,
+                Some(StandardTagKey::Artist) => self.artist = value,
+                Some(StandardTagKey::Album) => self.album = value,
+                _ => (),
+            }
+        }
+        if let Some(track) = probed.format.default_track() {
+            let params = &track.codec_params;
+            if let (Some(frames), Some(rate)) = (params.n_frames, params.sample_rate) {
+                self.duration = Some(frames as f64 / rate as f64);
+            }
+        }
+    }
+
+    /// The title, or the file name of a track without one
+    pub fn name(&self) -> String {
+        self.title.clone().unwrap_or_else(|| {
+            self.path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default()
+        })
+    }
+
+    /// The name, artist, album and duration on separate lines, those which are known
+    pub fn description(&self) -> String {
+        let mut lines = vec![self.name()];
+        match (&self.artist, &self.album) {
+            (Some(artist), Some(album)) => lines.push(format!("{} - {}", artist, album)),
+            (Some(tag), None) | (None, Some(tag)) => lines.push(tag.clone()),
+            (None, None) => (),
+        }
+        if let Some(duration) = self.duration {
+            lines.push(format_time(duration));
+        }
+        lines.join("\n")
+    }
+}
+
+/// The tracks to play, in order or shuffled
+#[derive(Debug, Clone)]
+pub struct Playlist {
+    tracks: Vec<Track>,
+    current: Option<usize>,
+    pub shuffle: bool,
+    pub repeat: Repeat,
+    // the tracks played before the current one, which previous goes back to when shuffling
+    history: Vec<usize>,
+    // the tracks already played in the current pass when shuffling, each one being played once
+    played: Vec<usize>,
+}
+
+impl Default for Playlist {
+    fn default() -> Self {
+        Self {
+            tracks: vec![],
+            current: None,
+            shuffle: false,
+            repeat: Repeat::Off,
+            history: vec![],
+            played: vec![],
+        }
+    }
+}
+
+impl Playlist {
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    /// The index of the track which is played, or will be when playing starts
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    pub fn current_track(&self) -> Option<&Track> {
+        self.tracks.get(self.current?)
+    }
+
+    /// Make a track the current one
+    pub fn select(&mut self, idx: usize) {
+        if idx < self.tracks.len() {
+            if let Some(current) = self.current {
+                self.history.push(current);
+            }
+            self.current = Some(idx);
+        }
+    }
+
+    /// Adds a file at the end, the first track added becoming the current one
+    pub fn add<P: AsRef<Path>>(&mut self, path: P) {
+        self.tracks.push(Track::new(path));
+        if self.current.is_none() {
+            self.current = Some(0);
+        }
+    }
+
+    /// Adds the audio files of a folder, sorted by name
+    pub fn add_folder<P: AsRef<Path>>(&mut self, dir: P) -> io::Result<()> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .is_some_and(|e| AUDIO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            self.add(path);
+        }
+        Ok(())
+    }
+
+    /// Removes a track, the next one becoming current if it was
+    pub fn remove(&mut self, idx: usize) {
+        if idx >= self.tracks.len() {
+            return;
+        }
+        self.tracks.remove(idx);
+        self.current = match self.current {
+            _ if self.tracks.is_empty() => None,
+            Some(c) if c > idx || c == self.tracks.len() => Some(c - 1),
+            current => current,
+        };
+        self.history = self
+            .history
+            .iter()
+            .filter(|&&i| i != idx)
+            .map(|&i| if i > idx { i - 1 } else { i })
+            .collect();
+        self.played = self
+            .played
+            .iter()
+            .filter(|&&i| i != idx)
+            .map(|&i| if i > idx { i - 1 } else { i })
+            .collect();
+    }
+
+    /// Moves a track to another place, e.g. after dragging it
+    pub fn move_track(&mut self, from: usize, to: usize) {
+        if from >= self.tracks.len() || to >= self.tracks.len() {
+            return;
+        }
+        let track = self.tracks.remove(from);
+        self.tracks.insert(to, track);
+        // the indices between the two places shift by one
+        let moved = |i: usize| match i {
+            i if i == from => to,
+            i if from < to && i > from && i <= to => i - 1,
+            i if to < from && i >= to && i < from => i + 1,
+            i => i,
+        };
+        self.current = self.current.map(moved);
+        self.history = self.history.iter().map(|&i| moved(i)).collect();
+        self.played = self.played.iter().map(|&i| moved(i)).collect();
+    }
+
+    /// Moves to the track to play next and returns it. At the end of a track, repeating one
+    /// plays it again, while moving on past the last track, or past the last unplayed one
+    /// when shuffling, stops unless repeating all
+    pub fn next(&mut self, track_ended: bool) -> Option<&Track> {
+        let current = self.current?;
+        let len = self.tracks.len();
+        let next = if track_ended && self.repeat == Repeat::One {
+            current
+        } else if self.shuffle && len > 1 {
+            if !self.played.contains(&current) {
+                self.played.push(current);
+            }
+            let mut unplayed: Vec<usize> =
+                (0..len).filter(|i| !self.played.contains(i)).collect();
+            if unplayed.is_empty() {
+                if track_ended && self.repeat != Repeat::All {
+                    self.played.clear();
+                    return None;
+                }
+                // a new pass, which doesn't start with the track just played
+                self.played.clear();
+                unplayed = (0..len).filter(|&i| i != current).collect();
+            }
+            *unplayed.choose(&mut rand::thread_rng())?
+        } else if current + 1 < len {
+            current + 1
+        } else if self.repeat == Repeat::All || !track_ended {
+            0
+        } else {
+            return None;
+        };
+        if next != current {
+            self.history.push(current);
+        }
+        self.current = Some(next);
+        self.current_track()
+    }
+
+    /// Moves to the track played before when shuffling, otherwise to the one above
+    pub fn previous(&mut self) -> Option<&Track> {
+        let current = self.current?;
+        self.current = Some(match self.history.pop() {
+            Some(prev) if self.shuffle => prev,
+            _ if current > 0 => current - 1,
+            _ => self.tracks.len() - 1,
+        });
+        self.current_track()
+    }
+
+    /// Writes the tracks to an extended M3U file, with their names and durations
+    pub fn save_m3u<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        writeln!(file, "#EXTM3U")?;
+        for track in &self.tracks {
+            let secs = track.duration.map_or(-1, |d| d.round() as i64);
+            let name = match &track.artist {
+                Some(artist) => format!("{} - {}", artist, track.name()),
+                None => track.name(),
+            };
+            // absolute paths, which don't depend on where the file is saved
+            let path = fs::canonicalize(&track.path).unwrap_or_else(|_| track.path.clone());
+            writeln!(file, "#EXTINF:{},{}", secs, name)?;
+            writeln!(file, "{}", path.display())?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the tracks with those of an M3U file, relative paths being relative to it
+    pub fn load_m3u<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let base = path.parent().unwrap_or(Path::new(""));
+        let file = BufReader::new(File::open(path)?);
+        let mut playlist = Self {
+            shuffle: self.shuffle,
+            repeat: self.repeat,
+            ..Self::default()
+        };
+        for line in file.lines() {
+            let line = line?;
+            let line = line.trim();
+            // comments and the #EXT directives, the tags being read from the files
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            playlist.add(base.join(line));
+        }
+        *self = playlist;
+        Ok(())
+    }
+}
+
+/// Formats seconds as minutes:seconds
+pub fn format_time(secs: f64) -> String {
+    let secs = secs.max(0.) as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
@@ -0,0 +1,262 @@
+use crate::playlist::{format_time, Playlist, Repeat};
+use fltk::{enums::*, prelude::*, *};
+use std::cell::RefCell;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+const BUTTON_H: i32 = 25;
+
+type Callback = Rc<RefCell<Option<Box<dyn FnMut(&mut PlaylistPanel)>>>>;
+
+/// A list of the tracks of a playlist with buttons to add, remove, load and save them.
+/// Tracks are reordered by dragging them
+#[derive(Clone)]
+pub struct PlaylistPanel {
+    grp: group::Group,
+    browser: browser::HoldBrowser,
+    playlist: Rc<RefCell<Playlist>>,
+    cb: Callback,
+}
+
+impl PlaylistPanel {
+    pub fn new(x: i32, y: i32, w: i32, h: i32, playlist: Rc<RefCell<Playlist>>) -> Self {
+        let grp = group::Group::new(x, y, w, h, "");
+        let mut browser = browser::HoldBrowser::new(x, y, w, h - 2 * BUTTON_H, "");
+        browser.set_column_char('\t');
+        browser.set_column_widths(&[w - 60, 50]);
+        browser.set_color(Color::from_u32(0x1c1c24));
+        browser.set_text_color(Color::White);
+        browser.set_selection_color(Color::from_u32(0x868db1));
+        browser.set_text_size(14);
+        let labels = ["Files...", "Folder...", "Remove", "Load...", "Save..."];
+        let bw = w / labels.len() as i32;
+        let mut buttons: Vec<button::Button> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, label)| {
+                button::Button::new(
+                    x + i as i32 * bw,
+                    y + h - 2 * BUTTON_H,
+                    bw,
+                    BUTTON_H,
+                    *label,
+                )
+            })
+            .collect();
+        let mut shuffle = button::CheckButton::new(x, y + h - BUTTON_H, w / 2, BUTTON_H, "Shuffle");
+        shuffle.set_label_color(Color::White);
+        let mut repeat = menu::Choice::new(x + w - 100, y + h - BUTTON_H, 100, BUTTON_H, "Repeat");
+        repeat.set_label_color(Color::White);
+        repeat.add_choice("Off|All|One");
+        repeat.set_value(0);
+        grp.end();
+        grp.resizable(&browser);
+
+        let mut s = Self {
+            grp,
+            browser,
+            playlist,
+            cb: Rc::from(RefCell::from(None)),
+        };
+        s.refresh();
+
+        buttons[0].set_callback({
+            let mut s = s.clone();
+            move |_| {
+                let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseMultiFile);
+                dlg.set_filter("Audio\t*.{mp3,wav,ogg,flac}");
+                dlg.show();
+                let was_empty = s.playlist.borrow().tracks().is_empty();
+                for path in dlg.filenames() {
+                    s.playlist.borrow_mut().add(path);
+                }
+                s.refresh();
+                // the first track added becomes the current one
+                if was_empty {
+                    s.changed();
+                }
+            }
+        });
+        buttons[1].set_callback({
+            let mut s = s.clone();
+            move |_| {
+                let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseDir);
+                dlg.show();
+                let dir = dlg.filename();
+                if dir.as_os_str().is_empty() {
+                    return;
+                }
+                let was_empty = s.playlist.borrow().tracks().is_empty();
+                let added = s.playlist.borrow_mut().add_folder(&dir);
+                if let Err(e) = added {
+                    dialog::alert_default(&format!("Couldn't read {}: {}", dir.display(), e));
+                }
+                s.refresh();
+                if was_empty {
+                    s.changed();
+                }
+            }
+        });
+        buttons[2].set_callback({
+            let mut s = s.clone();
+            move |_| {
+                let Some(idx) = s.selected() else {
+                    return;
+                };
+                let was_current = s.playlist.borrow().current() == Some(idx);
+                s.playlist.borrow_mut().remove(idx);
+                s.refresh();
+                if was_current {
+                    s.changed();
+                }
+            }
+        });
+        buttons[3].set_callback({
+            let mut s = s.clone();
+            move |_| {
+                let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseFile);
+                dlg.set_filter("Playlists\t*.m3u");
+                dlg.show();
+                let path = dlg.filename();
+                if path.as_os_str().is_empty() {
+                    return;
+                }
+                let loaded = s.playlist.borrow_mut().load_m3u(&path);
+                match loaded {
+                    Ok(()) => {
+                        s.refresh();
+                        s.changed();
+                    }
+                    Err(e) => {
+                        dialog::alert_default(&format!("Couldn't load {}: {}", path.display(), e))
+                    }
+                }
+            }
+        });
+        buttons[4].set_callback({
+            let s = s.clone();
+            move |_| {
+                let mut dlg = dialog::FileDialog::new(dialog::FileDialogType::BrowseSaveFile);
+                dlg.set_option(dialog::FileDialogOptions::SaveAsConfirm);
+                dlg.set_filter("Playlists\t*.m3u");
+                dlg.show();
+                let path = dlg.filename();
+                if path.as_os_str().is_empty() {
+                    return;
+                }
+                if let Err(e) = s.playlist.borrow().save_m3u(&path) {
+                    dialog::alert_default(&format!("Couldn't save {}: {}", path.display(), e));
+                }
+            }
+        });
+        shuffle.set_callback({
+            let s = s.clone();
+            move |b| s.playlist.borrow_mut().shuffle = b.is_checked()
+        });
+        repeat.set_callback({
+            let s = s.clone();
+            move |c| {
+                s.playlist.borrow_mut().repeat = match c.value() {
+                    1 => Repeat::All,
+                    2 => Repeat::One,
+                    _ => Repeat::Off,
+                }
+            }
+        });
+
+        // the track a drag started from. The browser selects the clicked line after this
+        // handler runs, so the source is taken on the first drag, not on the push
+        let mut dragged = None;
+        s.browser.handle({
+            let mut s = s.clone();
+            move |_, ev| match ev {
+                Event::Push => {
+                    dragged = None;
+                    false
+                }
+                Event::Drag => {
+                    if dragged.is_none() {
+                        dragged = s.selected();
+                    }
+                    false
+                }
+                Event::Released => {
+                    let to = s.selected();
+                    match (dragged.take(), to) {
+                        (Some(from), Some(to)) if from != to => {
+                            s.playlist.borrow_mut().move_track(from, to);
+                            s.refresh();
+                        }
+                        // double clicking a track makes it the current one
+                        (_, Some(idx)) if app::event_clicks() => {
+                            s.playlist.borrow_mut().select(idx);
+                            s.refresh();
+                            s.changed();
+                        }
+                        _ => (),
+                    }
+                    false
+                }
+                _ => false,
+            }
+        });
+        s
+    }
+
+    /// Lists the tracks again, the current one in bold
+    pub fn refresh(&mut self) {
+        let selected = self.browser.value();
+        self.browser.clear();
+        let playlist = self.playlist.borrow();
+        for (i, track) in playlist.tracks().iter().enumerate() {
+            let style = if playlist.current() == Some(i) {
+                "@b"
+            } else {
+                ""
+            };
+            let name = match &track.artist {
+                Some(artist) => format!("{} - {}", artist, track.name()),
+                None => track.name(),
+            };
+            let duration = track.duration.map(format_time).unwrap_or_default();
+            // @. stops the formatting, names may start with @
+            self.browser
+                .add(&format!("{}@.{}\t{}@.{}", style, name, style, duration));
+        }
+        if selected > 0 && selected <= self.browser.size() {
+            self.browser.select(selected);
+        }
+    }
+
+    /// Set a callback which is triggered when the user changes the current track:
+    /// by double clicking one, removing it, loading a playlist or adding to an empty one
+    pub fn set_callback<F: FnMut(&mut Self) + 'static>(&mut self, cb: F) {
+        *self.cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    // the index of the selected track
+    fn selected(&self) -> Option<usize> {
+        let line = self.browser.value();
+        (line > 0).then(|| line as usize - 1)
+    }
+
+    fn changed(&mut self) {
+        if let Some(cb) = self.cb.clone().borrow_mut().as_mut() {
+            cb(self);
+        }
+    }
+}
+
+impl Deref for PlaylistPanel {
+    type Target = group::Group;
+
+    fn deref(&self) -> &Self::Target {
+        &self.grp
+    }
+}
+
+impl DerefMut for PlaylistPanel {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.grp
+    }
+}
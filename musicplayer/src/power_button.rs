@@ -89,6 +89,15 @@ impl PowerButton {
         });
         Self { frm, on }
     }
+
+    pub fn is_on(&self) -> bool {
+        *self.on.borrow()
+    }
+
+    pub fn set_on(&mut self, on: bool) {
+        *self.on.borrow_mut() = on;
+        self.frm.redraw();
+    }
 }
 
 impl Deref for PowerButton {
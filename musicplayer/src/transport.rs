@@ -0,0 +1,150 @@
+use crate::playlist::format_time;
+use fltk::{button::*, enums::*, frame::*, group::*, prelude::*, valuator::*};
+use std::cell::{Cell, RefCell};
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+const WIDTH: i32 = 360;
+const LABEL_W: i32 = 50;
+
+/// What the user asked the transport for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    PlayPause,
+    Stop,
+    /// Jump to a position in seconds
+    Seek(f64),
+}
+
+type Callback = Rc<RefCell<Option<Box<dyn FnMut(&mut Transport, Action)>>>>;
+
+/// Play/pause and stop buttons under a seek bar between the elapsed and remaining times
+#[derive(Clone)]
+pub struct Transport {
+    grp: Group,
+    play: Button,
+    seek: HorNiceSlider,
+    elapsed: Frame,
+    remaining: Frame,
+    // while the seek bar is held it doesn't follow the playback
+    held: Rc<Cell<bool>>,
+    cb: Callback,
+}
+
+impl Transport {
+    pub fn new(x: i32, y: i32) -> Self {
+        let grp = Group::new(x, y, WIDTH, 60, "");
+        let mut elapsed = Frame::new(x, y, LABEL_W, 20, "0:00");
+        let mut seek = HorNiceSlider::new(x + LABEL_W, y, WIDTH - 2 * LABEL_W, 20, "");
+        let mut remaining = Frame::new(x + WIDTH - LABEL_W, y, LABEL_W, 20, "-0:00");
+        let mut play = Button::new(x + WIDTH / 2 - 45, y + 28, 40, 30, "@>");
+        let mut stop = Button::new(x + WIDTH / 2 + 5, y + 28, 40, 30, "@square");
+        grp.end();
+        for label in [&mut elapsed, &mut remaining] {
+            label.set_label_color(Color::White);
+            label.set_label_size(12);
+        }
+        seek.set_frame(FrameType::RFlatBox);
+        seek.set_color(Color::from_u32(0x868db1));
+        seek.set_selection_color(Color::Blue);
+        seek.set_range(0., 0.);
+        for b in [&mut play, &mut stop] {
+            b.set_frame(FrameType::FlatBox);
+            b.set_color(Color::Black);
+            b.set_label_color(Color::White);
+        }
+
+        let s = Self {
+            grp,
+            play,
+            seek,
+            elapsed,
+            remaining,
+            held: Rc::from(Cell::from(false)),
+            cb: Rc::from(RefCell::from(None)),
+        };
+        s.play.clone().set_callback({
+            let mut s = s.clone();
+            move |_| s.act(Action::PlayPause)
+        });
+        stop.set_callback({
+            let mut s = s.clone();
+            move |_| s.act(Action::Stop)
+        });
+        // clicking the bar jumps there, dragging it keeps jumping
+        s.seek.clone().set_callback({
+            let mut s = s.clone();
+            move |b| s.act(Action::Seek(b.value()))
+        });
+        s.seek.clone().handle({
+            let held = s.held.clone();
+            move |_, ev| {
+                match ev {
+                    Event::Push => held.set(true),
+                    Event::Released => held.set(false),
+                    _ => (),
+                }
+                false
+            }
+        });
+        s
+    }
+
+    /// Shows the position of the playback and the length of the track, in seconds
+    pub fn set_position(&mut self, pos: f64, length: f64) {
+        if self.held.get() {
+            return;
+        }
+        let pos = pos.clamp(0., length);
+        if self.seek.maximum() != length {
+            self.seek.set_range(0., length);
+        }
+        if self.seek.value() != pos {
+            self.seek.set_value(pos);
+            self.seek.redraw();
+        }
+        set_text(&mut self.elapsed, &format_time(pos));
+        set_text(
+            &mut self.remaining,
+            &format!("-{}", format_time(length - pos)),
+        );
+    }
+
+    /// Shows the pause button while playing, and the play button otherwise
+    pub fn set_playing(&mut self, playing: bool) {
+        set_text(&mut self.play, if playing { "@||" } else { "@>" });
+    }
+
+    /// Set a callback which is triggered when a button is clicked or the seek bar is moved
+    pub fn set_callback<F: FnMut(&mut Self, Action) + 'static>(&mut self, cb: F) {
+        *self.cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    fn act(&mut self, action: Action) {
+        if let Some(cb) = self.cb.clone().borrow_mut().as_mut() {
+            cb(self, action);
+        }
+    }
+}
+
+// change a label only when it differs, as it's updated while playing
+fn set_text<W: WidgetExt>(w: &mut W, text: &str) {
+    if w.label() != text {
+        w.set_label(text);
+        w.redraw();
+    }
+}
+
+impl Deref for Transport {
+    type Target = Group;
+
+    fn deref(&self) -> &Self::Target {
+        &self.grp
+    }
+}
+
+impl DerefMut for Transport {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.grp
+    }
+}
@@ -0,0 +1,138 @@
+use fltk::{enums::*, prelude::*, *};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ops::{Deref, DerefMut};
+use std::rc::Rc;
+
+// the bars the frequency bins are grouped in
+const BARS: usize = 32;
+// the columns of the waveform added with each update, the older ones scrolling left
+const COLUMNS_PER_UPDATE: usize = 4;
+// the bars falling slower than the sound, so that they're readable
+const FALLOFF: f32 = 0.85;
+// the quietest level shown, in decibels
+const FLOOR_DB: f32 = -60.;
+
+struct Data {
+    // the height of each bar between 0 and 1
+    bars: Vec<f32>,
+    // the lowest and highest sample of each column of the waveform
+    columns: VecDeque<(f32, f32)>,
+}
+
+/// A spectrum bar graph over a scrolling waveform of the audio being played
+#[derive(Clone)]
+pub struct Visualizer {
+    frm: frame::Frame,
+    data: Rc<RefCell<Data>>,
+}
+
+impl Visualizer {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        let mut frm = frame::Frame::new(x, y, w, h, "");
+        frm.set_frame(FrameType::FlatBox);
+        frm.set_color(Color::from_u32(0x1c1c24));
+        let data = Rc::from(RefCell::from(Data {
+            bars: vec![0.; BARS],
+            columns: VecDeque::new(),
+        }));
+        frm.draw({
+            let data = data.clone();
+            move |f| draw_data(f, &data.borrow())
+        });
+        Self { frm, data }
+    }
+
+    /// Shows the spectrum and the latest samples, as given by soloud's visualization
+    pub fn update(&mut self, fft: &[f32], wave: &[f32]) {
+        let mut data = self.data.borrow_mut();
+        let levels = group_bins(fft);
+        for (bar, level) in data.bars.iter_mut().zip(levels) {
+            *bar = level.max(*bar * FALLOFF);
+        }
+        let chunk = (wave.len() / COLUMNS_PER_UPDATE).max(1);
+        for samples in wave.chunks(chunk) {
+            let min = samples.iter().copied().fold(0., f32::min);
+            let max = samples.iter().copied().fold(0., f32::max);
+            data.columns.push_back((min, max));
+        }
+        let width = self.frm.w().max(0) as usize;
+        while data.columns.len() > width {
+            data.columns.pop_front();
+        }
+        drop(data);
+        self.frm.redraw();
+    }
+
+    /// Empties the graphs, e.g. when the playback stops
+    pub fn clear(&mut self) {
+        let mut data = self.data.borrow_mut();
+        if data.columns.is_empty() && data.bars.iter().all(|&bar| bar == 0.) {
+            return;
+        }
+        data.bars.iter_mut().for_each(|bar| *bar = 0.);
+        data.columns.clear();
+        drop(data);
+        self.frm.redraw();
+    }
+}
+
+// average the bins in bars which are wider at higher frequencies, as hearing is logarithmic,
+// scaled from the floor to 0 dB
+fn group_bins(fft: &[f32]) -> Vec<f32> {
+    if fft.is_empty() {
+        return vec![0.; BARS];
+    }
+    let len = fft.len() as f32;
+    (0..BARS)
+        .map(|i| {
+            // skipping the constant offset in the first bin
+            let start = (len.powf(i as f32 / BARS as f32) as usize).min(fft.len() - 1);
+            let end = (len.powf((i + 1) as f32 / BARS as f32) as usize).clamp(start + 1, fft.len());
+            let mean = fft[start..end].iter().sum::<f32>() / (end - start) as f32;
+            let db = 20. * mean.max(f32::MIN_POSITIVE).log10();
+            ((db - FLOOR_DB) / -FLOOR_DB).clamp(0., 1.)
+        })
+        .collect()
+}
+
+// the spectrum in the upper half and the waveform in the lower half
+fn draw_data(f: &frame::Frame, data: &Data) {
+    draw::draw_box(f.frame(), f.x(), f.y(), f.w(), f.h(), f.color());
+    let half = f.h() / 2;
+
+    let bar_w = f.w() / BARS as i32;
+    draw::set_draw_color(Color::from_u32(0x868db1));
+    for (i, bar) in data.bars.iter().enumerate() {
+        let h = (bar * (half - 2) as f32) as i32;
+        draw::draw_rectf(f.x() + i as i32 * bar_w + 1, f.y() + half - h, bar_w - 2, h);
+    }
+
+    let mid = f.y() + half + half / 2;
+    let scale = (half / 2 - 1) as f32;
+    draw::set_draw_color(Color::from_u32(0x2c2c38));
+    draw::draw_line(f.x(), mid, f.x() + f.w() - 1, mid);
+    draw::set_draw_color(Color::Blue);
+    // the newest column on the right
+    let start = f.x() + f.w() - data.columns.len() as i32;
+    for (i, (min, max)) in data.columns.iter().enumerate() {
+        let x = start + i as i32;
+        let top = mid - (max.clamp(-1., 1.) * scale) as i32;
+        let bottom = mid - (min.clamp(-1., 1.) * scale) as i32;
+        draw::draw_line(x, top, x, bottom);
+    }
+}
+
+impl Deref for Visualizer {
+    type Target = frame::Frame;
+
+    fn deref(&self) -> &Self::Target {
+        &self.frm
+    }
+}
+
+impl DerefMut for Visualizer {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.frm
+    }
+}
@@ -0,0 +1,322 @@
+use crate::vt::Terminal;
+use serde_json::{json, Value};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+// the slowest and fastest playback speeds
+const SPEEDS: (f64, f64) = (0.25, 16.);
+
+/// An event of an asciicast recording
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// Output of the program
+    Output(String),
+    /// The terminal was resized to (columns, rows)
+    Resize(usize, usize),
+}
+
+/// An asciicast v2 recording: a JSON header line, then a `[time, code, data]` line per event
+#[derive(Debug, Clone)]
+pub struct Cast {
+    pub width: usize,
+    pub height: usize,
+    /// The events with their time in seconds, in order
+    pub events: Vec<(f64, Event)>,
+}
+
+impl Cast {
+    /// Reads a recording from a .cast file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::parse(&text)
+    }
+
+    /// Parses a recording, keeping its output and resize events
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines.next().ok_or("The file is empty")?;
+        let header: Value = serde_json::from_str(header).map_err(|e| e.to_string())?;
+        if header["version"] != 2 {
+            return Err("Not an asciicast v2 recording".to_string());
+        }
+        let size = |key: &str| {
+            header[key]
+                .as_u64()
+                .filter(|&n| n > 0)
+                .map(|n| n as usize)
+                .ok_or_else(|| format!("The header has no {}", key))
+        };
+        let (width, height) = (size("width")?, size("height")?);
+        let mut events = vec![];
+        for (n, line) in lines {
+            let event: Value =
+                serde_json::from_str(line).map_err(|e| format!("Line {}: {}", n + 1, e))?;
+            let (Some(time), Some(code), Some(data)) =
+                (event[0].as_f64(), event[1].as_str(), event[2].as_str())
+            else {
+                return Err(format!("Line {}: not an event", n + 1));
+            };
+            match code {
+                "o" => events.push((time, Event::Output(data.to_string()))),
+                "r" => {
+                    if let Some((w, h)) = data.split_once('x') {
+                        if let (Ok(w @ 1..), Ok(h @ 1..)) = (w.parse(), h.parse()) {
+                            events.push((time, Event::Resize(w, h)));
+                        }
+                    }
+                }
+                // input and markers don't show
+                _ => (),
+            }
+        }
+        events.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Ok(Self {
+            width,
+            height,
+            events,
+        })
+    }
+
+    /// The time of the last event, in seconds
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0., |(time, _)| *time)
+    }
+}
+
+/// Writes the output of a program to a .cast file as it arrives
+pub struct Recorder {
+    file: BufWriter<File>,
+    start: Instant,
+    // the start of a UTF-8 sequence whose end is in the next output
+    pending: Vec<u8>,
+}
+
+impl Recorder {
+    /// Creates the file and writes the header for a terminal of this size
+    pub fn create<P: AsRef<Path>>(path: P, cols: usize, rows: usize) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let header = json!({
+            "version": 2,
+            "width": cols,
+            "height": rows,
+            "timestamp": timestamp,
+            "env": { "TERM": "xterm-256color" },
+        });
+        let mut file = BufWriter::new(File::create(path)?);
+        writeln!(file, "{}", header)?;
+        file.flush()?;
+        Ok(Self {
+            file,
+            start: Instant::now(),
+            pending: vec![],
+        })
+    }
+
+    /// Records output of the program
+    pub fn output(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.pending.extend_from_slice(bytes);
+        // events are JSON strings, a sequence cut between reads waits for its end
+        let valid = match std::str::from_utf8(&self.pending) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => self.pending.len(),
+        };
+        if valid == 0 {
+            return Ok(());
+        }
+        let text = String::from_utf8_lossy(&self.pending[..valid]).into_owned();
+        self.pending.drain(..valid);
+        self.event("o", &text)
+    }
+
+    /// Records a resize of the terminal
+    pub fn resize(&mut self, cols: usize, rows: usize) -> io::Result<()> {
+        self.event("r", &format!("{}x{}", cols, rows))
+    }
+
+    fn event(&mut self, code: &str, data: &str) -> io::Result<()> {
+        let time = self.start.elapsed().as_secs_f64();
+        writeln!(self.file, "{}", json!([time, code, data]))?;
+        self.file.flush()
+    }
+}
+
+/// Replays a recording into a terminal, which needs no program
+pub struct Player {
+    cast: Cast,
+    scrollback: usize,
+    // the index of the next event to feed
+    next: usize,
+    // the position in the recording, in seconds
+    time: f64,
+    speed: f64,
+    paused: bool,
+}
+
+impl Player {
+    /// Creates a player at the start of the recording, the terminal keeping this many lines
+    /// above the screen
+    pub fn new(cast: Cast, scrollback: usize) -> Self {
+        Self {
+            cast,
+            scrollback,
+            next: 0,
+            time: 0.,
+            speed: 1.,
+            paused: false,
+        }
+    }
+
+    /// Clears the terminal to the size the recording starts with
+    pub fn rewind(&mut self, vt: &mut Terminal) {
+        *vt = Terminal::new(self.cast.width, self.cast.height);
+        vt.set_scrollback_limit(self.scrollback);
+        self.next = 0;
+        self.time = 0.;
+    }
+
+    /// Moves forward by some real time, scaled by the speed, unless paused
+    pub fn advance(&mut self, vt: &mut Terminal, secs: f64) {
+        if !self.paused {
+            self.seek(vt, self.time + secs * self.speed);
+        }
+    }
+
+    /// Feeds the events up to a position in seconds, going back replays from the start.
+    /// The player pauses at the end
+    pub fn seek(&mut self, vt: &mut Terminal, time: f64) {
+        let time = time.clamp(0., self.duration());
+        if time < self.time {
+            self.rewind(vt);
+        }
+        while let Some((at, event)) = self.cast.events.get(self.next) {
+            if *at > time {
+                break;
+            }
+            match event {
+                Event::Output(text) => vt.feed(text.as_bytes()),
+                Event::Resize(cols, rows) => vt.resize(*cols, *rows),
+            }
+            self.next += 1;
+        }
+        // there's no program to answer
+        vt.take_replies();
+        self.time = time;
+        if self.finished() {
+            self.paused = true;
+        }
+    }
+
+    /// Pauses or resumes, resuming at the end plays again from the start
+    pub fn toggle_pause(&mut self, vt: &mut Terminal) {
+        if self.finished() {
+            self.rewind(vt);
+        }
+        self.paused = !self.paused;
+    }
+
+    /// Multiplies the speed, which stays between a quarter and 16 times the recorded one
+    pub fn change_speed(&mut self, factor: f64) {
+        self.speed = (self.speed * factor).clamp(SPEEDS.0, SPEEDS.1);
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.cast.duration()
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Whether every event was fed
+    pub fn finished(&self) -> bool {
+        self.next >= self.cast.events.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a 10x3 recording which is resized to 20x4, with an input event which isn't replayed
+    const CAST: &str = r#"{"version": 2, "width": 10, "height": 3}
+[0.5, "o", "hello"]
+[1.0, "o", "\r\nworld"]
+[1.5, "r", "20x4"]
+[2.0, "o", "\u001b[1;1Hx"]
+[2.5, "i", "ignored"]
+"#;
+
+    // the text of a row of the screen, without trailing spaces
+    fn row(vt: &Terminal, row: usize) -> String {
+        let line = vt.history_line(vt.screen_top() + row).unwrap_or_default();
+        let text: String = line.iter().map(|c| c.ch).collect();
+        text.trim_end().to_string()
+    }
+
+    #[test]
+    fn parse() {
+        let cast = Cast::parse(CAST).unwrap();
+        assert_eq!((cast.width, cast.height), (10, 3));
+        assert_eq!(cast.events.len(), 4);
+        assert!(matches!(cast.events[2], (_, Event::Resize(20, 4))));
+        assert_eq!(cast.duration(), 2.);
+        assert!(Cast::parse(r#"{"version": 1, "width": 10, "height": 3}"#).is_err());
+        assert!(Cast::parse("{\"version\": 2, \"width\": 10, \"height\": 3}\n[0.5]").is_err());
+    }
+
+    #[test]
+    fn seek_forward() {
+        let mut player = Player::new(Cast::parse(CAST).unwrap(), 100);
+        let mut vt = Terminal::new(80, 24);
+        player.rewind(&mut vt);
+        assert_eq!((vt.cols(), vt.rows()), (10, 3));
+
+        player.seek(&mut vt, 1.);
+        assert_eq!(row(&vt, 0), "hello");
+        assert_eq!(row(&vt, 1), "world");
+        assert_eq!(vt.cursor(), (1, 5));
+        assert!(!player.finished());
+
+        player.seek(&mut vt, 10.);
+        assert_eq!((vt.cols(), vt.rows()), (20, 4));
+        assert_eq!(row(&vt, 0), "xello");
+        assert_eq!(row(&vt, 1), "world");
+        assert_eq!(vt.cursor(), (0, 1));
+        assert_eq!(player.time(), 2.);
+        assert!(player.finished());
+        assert!(player.paused());
+    }
+
+    #[test]
+    fn seek_backward() {
+        let mut player = Player::new(Cast::parse(CAST).unwrap(), 100);
+        let mut vt = Terminal::new(80, 24);
+        player.rewind(&mut vt);
+        player.seek(&mut vt, 2.);
+
+        // replayed from the start, at the recording's first size
+        player.seek(&mut vt, 0.5);
+        assert_eq!((vt.cols(), vt.rows()), (10, 3));
+        assert_eq!(row(&vt, 0), "hello");
+        assert_eq!(row(&vt, 1), "");
+        assert_eq!(vt.cursor(), (0, 5));
+        assert!(!player.finished());
+    }
+}
@@ -1,170 +1,65 @@
-mod term {
-    use fltk::{
-        enums::*,
-        prelude::*,
-        *,
-    };
-    use portable_pty::{
-        native_pty_system,
-        CommandBuilder,
-        PtySize
-    };
-    use std::io::{
-        Read,
-        Write
-    };
+mod cast;
+mod sessions;
+mod term;
+mod vt;
 
-    pub struct AnsiTerm {
-        st: text::SimpleTerminal,
-    }
-
-    impl Default for AnsiTerm {
-        fn default() -> Self {
-            AnsiTerm::new(0, 0, 0, 0, None)
-        }
-    }
-
-    impl AnsiTerm {
-        pub fn new<L: Into<Option<&'static str>>>(
-            x: i32,
-            y: i32,
-            w: i32,
-            h: i32,
-            label: L,
-        ) -> Self {
-            let mut st = text::SimpleTerminal::new(x, y, w, h, label);
-            // SimpleTerminal handles many common ansi escape sequence
-            st.set_ansi(true);
-            let pair = native_pty_system()
-                .openpty(PtySize {
-                    cols: 80,
-                    rows: 24,
-                    pixel_width: 80 * 10,
-                    pixel_height: 24 * 16,
-                })
-                .unwrap();
-
-            let cmd = if cfg!(target_os = "windows") {
-                CommandBuilder::new("cmd.exe")
-            } else {
-                let mut cmd = CommandBuilder::new("/bin/bash");
-                cmd.args(["-i"]);
-                cmd
-            };
-
-            let mut child = pair.slave.spawn_command(cmd).unwrap();
-            let mut writer = pair.master.try_clone_writer().unwrap();
-            let mut reader = pair.master.try_clone_reader().unwrap();
-
-            std::thread::spawn({
-                let mut st = st.clone();
-                move || {
-                    while child.try_wait().is_ok() {
-                        let mut msg = [0u8; 1024];
-                        if let Ok(sz) = reader.read(&mut msg) {
-                            let msg = &msg[0..sz];
-                            // we want to handle some escape sequences that the default SimpleTerminal doesn't
-                            format(msg, &mut st);
-                            app::awake();
-                        }
-                        std::thread::sleep(std::time::Duration::from_millis(30));
-                    }
-                }
-            });
-
-            let mut cmd = String::new();
-            st.handle(move |t, ev| {
-                let mut buf = t.buffer().unwrap();
-                let mut sbuf = t.style_buffer().unwrap();
-                match ev {
-                    Event::KeyDown => match app::event_key() {
-                        Key::Enter => {
-                            let len = cmd.len() as i32;
-                            let text_len = t.text().len() as i32;
-                            buf.remove(text_len - len, text_len);
-                            sbuf.remove(text_len - len, text_len);
-                            writer.write_all(cmd.as_bytes()).unwrap();
-                            writer.write_all(b"\n").unwrap();
-                            cmd.clear();
-                            true
-                        }
-                        Key::BackSpace => {
-                            if !cmd.is_empty() {
-                                let c = cmd.pop().unwrap();
-                                let len = if c.is_ascii() {
-                                    1
-                                } else {
-                                    utils::char_len(c) as i32
-                                };
-                                let text_len = t.text().len() as i32;
-                                buf.remove(text_len - len, text_len);
-                                sbuf.remove(text_len - len, text_len);
-                                true
-                            } else {
-                                false
-                            }
-                        }
-                        _ => {
-                            if let Some(ch) = app::event_text().chars().next() {
-                                if app::compose().is_some() {
-                                    let temp = ch.to_string();
-                                    cmd.push_str(&temp);
-                                    t.append(&temp);
-                                    true
-                                } else {
-                                    false
-                                }
-                            } else {
-                                false
-                            }
-                        }
-                    },
-                    Event::KeyUp => {
-                        if app::event_state() == Shortcut::Ctrl {
-                            let key = app::event_key();
-                            if key != Key::ControlL && key != Key::ControlR {
-                                if let Some(ch) = char::from_u32(key.bits() as u32 - 96) {
-                                    writer.write_all(&[ch as u8]).unwrap();
-                                }
-                            }
-                        }
-                        false
-                    }
-                    _ => false,
-                }
-            });
-            Self { st }
-        }
-    }
-
-    fltk::widget_extends!(AnsiTerm, text::SimpleTerminal, st);
-    fn format(msg: &[u8], st: &mut text::SimpleTerminal) {
-        // handles the sticky title-bell sequence
-        if let Some(pos0) = msg.windows(4).position(|m| m == b"\x1b]0;") {
-            let mut pos1 = pos0;
-            while pos1 < msg.len() && msg[pos1] != b'[' {
-                pos1 += 1;
-            }
-            st.append2(&msg[0..pos0]);
-            st.append2(&msg[pos1 - 1..]);
-        } else {
-            st.append2(msg);
-        }
-    }
-}
+use sessions::{Message, Sessions};
 
 const WIDTH: i32 = 800;
 const HEIGHT: i32 = 600;
+const SCROLLBACK: usize = 10_000;
+const MENU_H: i32 = 25;
 
 fn main() {
     use fltk::{prelude::*, *};
     let a = app::App::default();
-    let mut w = window::Window::default().with_size(WIDTH, HEIGHT);
-    w.make_resizable(true);
-    crate::term::AnsiTerm::default()
-        .with_size(WIDTH - 4, HEIGHT - 4)
-        .center_of_parent();
+    let mut w = window::Window::default()
+        .with_size(WIDTH, HEIGHT)
+        .with_label("Terminal");
+    let (s, r) = app::channel::<Message>();
+    let mut menu = menu::MenuBar::new(0, 0, WIDTH, MENU_H, None);
+    // Ctrl+Shift shortcuts, which the terminals leave to the menu
+    let items = [
+        ("&Tab/&New", 't', Message::NewTab),
+        ("&Tab/&Rename...", 'r', Message::RenameTab),
+        ("&Tab/&Close", 'q', Message::CloseTab),
+        ("&Pane/Split &right", 'e', Message::SplitRight),
+        ("&Pane/Split &down", 'o', Message::SplitDown),
+        ("&Pane/&Next", 'n', Message::NextPane),
+        ("&Pane/&Previous", 'p', Message::PrevPane),
+        ("&Pane/&Close", 'w', Message::ClosePane),
+        ("&Recording/&Record pane...", 's', Message::Record),
+        ("&Recording/&Stop recording", 'x', Message::StopRecording),
+        ("&Recording/&Play...", 'l', Message::Play),
+    ];
+    for (label, key, msg) in items {
+        let shortcut = enums::Shortcut::Ctrl | enums::Shortcut::Shift | key;
+        menu.add_emit(label, shortcut, menu::MenuFlag::Normal, s, msg);
+    }
+    // terminal [-C dir] [program [args...]]
+    let mut args = std::env::args().skip(1).peekable();
+    let mut builder = crate::term::AnsiTerm::builder().scrollback(SCROLLBACK);
+    if args.peek().map(String::as_str) == Some("-C") {
+        args.next();
+        if let Some(dir) = args.next() {
+            builder = builder.cwd(dir);
+        }
+    }
+    if let Some(program) = args.next() {
+        builder = builder.shell(&program).args(args);
+    }
+    let mut sessions = Sessions::new(0, MENU_H, WIDTH, HEIGHT - MENU_H, builder, s);
     w.end();
+    w.resizable(&*sessions);
     w.show();
-    a.run().unwrap();
+    while a.wait() {
+        while let Some(msg) = r.recv() {
+            sessions.handle(msg);
+        }
+        // the window closes with its last tab
+        if sessions.is_empty() {
+            w.hide();
+        }
+        sessions.update();
+    }
 }
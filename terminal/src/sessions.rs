@@ -0,0 +1,344 @@
+use crate::cast::Cast;
+use crate::term::{AnsiTerm, AnsiTermBuilder};
+use fltk::{prelude::*, *};
+
+// the height of the tab bar
+const TAB_H: i32 = 25;
+// the label of a tab whose program set no title
+const UNTITLED: &str = "Shell";
+
+/// The actions of the menu, and the exits of the programs of the panes
+#[derive(Debug, Clone, Copy)]
+pub enum Message {
+    NewTab,
+    CloseTab,
+    RenameTab,
+    SplitRight,
+    SplitDown,
+    ClosePane,
+    NextPane,
+    PrevPane,
+    Record,
+    StopRecording,
+    /// Open a recording in a new tab
+    Play,
+    /// The program of a pane exited, with whether it succeeded
+    Exited(u64, bool),
+}
+
+// a terminal with the id its exit is reported with
+struct Pane {
+    id: u64,
+    term: AnsiTerm,
+}
+
+struct Tab {
+    group: group::Flex,
+    // in the order they were opened, splits putting the new pane after the split one
+    panes: Vec<Pane>,
+    // the pane which last had the focus
+    focus: usize,
+    // set by renaming the tab, replacing the title of its program
+    name: Option<String>,
+}
+
+/// Defines tabs of terminals, each tab being split in panes running their own program
+pub struct Sessions {
+    tabs: group::Tabs,
+    list: Vec<Tab>,
+    builder: AnsiTermBuilder,
+    sender: app::Sender<Message>,
+    next_id: u64,
+}
+
+impl Sessions {
+    /// Creates the tabs with a first tab, the terminals running the program of the builder
+    pub fn new(
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        builder: AnsiTermBuilder,
+        sender: app::Sender<Message>,
+    ) -> Self {
+        let tabs = group::Tabs::new(x, y, w, h, None);
+        tabs.end();
+        let mut s = Self {
+            tabs,
+            list: vec![],
+            builder,
+            sender,
+            next_id: 0,
+        };
+        let term = s.builder.clone().build();
+        s.new_tab(term);
+        s
+    }
+
+    /// Whether the last tab was closed
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    /// Carry out an action of the menu, or ask what to do with a pane whose program exited
+    pub fn handle(&mut self, msg: Message) {
+        match msg {
+            Message::NewTab => self.new_tab(self.builder.clone().build()),
+            Message::CloseTab => {
+                if let Some(t) = self.current() {
+                    self.close_tab(t);
+                }
+            }
+            Message::RenameTab => self.rename_tab(),
+            Message::SplitRight => self.split(true),
+            Message::SplitDown => self.split(false),
+            Message::ClosePane => {
+                if let Some(t) = self.current() {
+                    self.close_pane(t, self.list[t].focus);
+                }
+            }
+            Message::NextPane => self.cycle_focus(1),
+            Message::PrevPane => self.cycle_focus(-1),
+            Message::Record => self.record(),
+            Message::StopRecording => {
+                if let Some(t) = self.current() {
+                    let tab = &mut self.list[t];
+                    tab.panes[tab.focus].term.stop_recording();
+                }
+            }
+            Message::Play => self.play(),
+            Message::Exited(id, success) => self.exited(id, success),
+        }
+    }
+
+    /// Keeps the labels of the tabs on their names, or on the title set by the program
+    /// of their focused pane
+    pub fn update(&mut self) {
+        let mut changed = false;
+        for tab in &mut self.list {
+            if let Some(p) = tab.panes.iter().position(|pane| pane.term.has_focus()) {
+                tab.focus = p;
+            }
+            let label = match &tab.name {
+                Some(name) => name.clone(),
+                None => match tab.panes[tab.focus].term.title() {
+                    title if title.is_empty() => UNTITLED.to_string(),
+                    title => title,
+                },
+            };
+            // @ starts a symbol in labels, e.g. in the user@host titles of shells
+            let label = label.replace('@', "@@");
+            if tab.group.label() != label {
+                tab.group.set_label(&label);
+                changed = true;
+            }
+        }
+        if changed {
+            self.tabs.redraw();
+        }
+    }
+
+    fn new_tab(&mut self, term: AnsiTerm) {
+        self.tabs.begin();
+        let mut group = group::Flex::new(
+            self.tabs.x(),
+            self.tabs.y() + TAB_H,
+            self.tabs.w(),
+            self.tabs.h() - TAB_H,
+            UNTITLED,
+        )
+        .row();
+        group.end();
+        group.add(&*term);
+        let mut pane = self.pane(term);
+        self.tabs.end();
+        self.tabs.set_value(&group).ok();
+        self.tabs.redraw();
+        pane.term.take_focus().ok();
+        self.list.push(Tab {
+            group,
+            panes: vec![pane],
+            focus: 0,
+            name: None,
+        });
+    }
+
+    // kill the programs of a tab before deleting it, showing the next tab
+    fn close_tab(&mut self, t: usize) {
+        let mut tab = self.list.remove(t);
+        for pane in &mut tab.panes {
+            pane.term.kill();
+        }
+        self.tabs.remove(&tab.group);
+        group::Flex::delete(tab.group);
+        if !self.list.is_empty() {
+            let t = t.min(self.list.len() - 1);
+            self.tabs.set_value(&self.list[t].group).ok();
+            self.focus(t, self.list[t].focus);
+        }
+        self.tabs.redraw();
+    }
+
+    fn rename_tab(&mut self) {
+        let Some(t) = self.current() else {
+            return;
+        };
+        let tab = &mut self.list[t];
+        let name = tab.name.clone().unwrap_or_default();
+        // an empty name goes back to following the program's title
+        if let Some(name) = dialog::input_default("Tab name, empty to use the title:", &name) {
+            tab.name = Some(name).filter(|name| !name.is_empty());
+        }
+    }
+
+    // put a new pane next to the focused one, to its right or below it,
+    // both sharing the place of the split pane
+    fn split(&mut self, right: bool) {
+        let Some(t) = self.current() else {
+            return;
+        };
+        let p = self.list[t].focus;
+        let term = self.list[t].panes[p].term.clone();
+        let Some(mut parent) = term.parent() else {
+            return;
+        };
+        let idx = parent.find(&*term);
+        let mut flex = group::Flex::new(term.x(), term.y(), term.w(), term.h(), None);
+        flex = if right { flex.row() } else { flex.column() };
+        flex.end();
+        flex.add(&*term);
+        let new = self.builder.clone().build();
+        flex.add(&*new);
+        let mut pane = self.pane(new);
+        parent.insert(&flex, idx);
+        relayout(&mut parent);
+        pane.term.take_focus().ok();
+        self.list[t].panes.insert(p + 1, pane);
+        self.list[t].focus = p + 1;
+    }
+
+    // kill the program of a pane before deleting it, closing its tab if it was the last one
+    fn close_pane(&mut self, t: usize, p: usize) {
+        let mut pane = self.list[t].panes.remove(p);
+        pane.term.kill();
+        if self.list[t].panes.is_empty() {
+            self.close_tab(t);
+            return;
+        }
+        if let Some(mut parent) = pane.term.parent() {
+            parent.remove(&*pane.term);
+            frame::Frame::delete((*pane.term).clone());
+            // a split left with one pane gives it its place
+            match (parent.child(0), parent.parent()) {
+                (Some(child), Some(mut outer)) if parent.children() == 1 => {
+                    let idx = outer.find(&parent);
+                    outer.insert(&child, idx);
+                    group::Group::delete(parent);
+                    relayout(&mut outer);
+                }
+                _ => relayout(&mut parent),
+            }
+        }
+        self.focus(t, p.min(self.list[t].panes.len() - 1));
+    }
+
+    // record the focused pane to a file chosen by the user
+    fn record(&mut self) {
+        let Some(t) = self.current() else {
+            return;
+        };
+        let mut dlg = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseSaveFile);
+        dlg.set_option(dialog::NativeFileChooserOptions::SaveAsConfirm);
+        dlg.set_filter("*.cast");
+        dlg.show();
+        let path = dlg.filename();
+        if path.as_os_str().is_empty() {
+            return;
+        }
+        let tab = &mut self.list[t];
+        if let Err(e) = tab.panes[tab.focus].term.record(&path) {
+            dialog::alert_default(&format!("Couldn't record to {}: {}", path.display(), e));
+        }
+    }
+
+    // replay a recording chosen by the user in a new tab named after it
+    fn play(&mut self) {
+        let mut dlg = dialog::NativeFileChooser::new(dialog::NativeFileChooserType::BrowseFile);
+        dlg.set_filter("*.cast");
+        dlg.show();
+        let path = dlg.filename();
+        if path.as_os_str().is_empty() {
+            return;
+        }
+        match Cast::load(&path) {
+            Ok(cast) => {
+                self.new_tab(AnsiTerm::replay(cast));
+                if let (Some(tab), Some(name)) = (self.list.last_mut(), path.file_name()) {
+                    tab.name = Some(name.to_string_lossy().into_owned());
+                }
+            }
+            Err(e) => dialog::alert_default(&format!("Couldn't play {}: {}", path.display(), e)),
+        }
+    }
+
+    fn cycle_focus(&mut self, step: isize) {
+        let Some(t) = self.current() else {
+            return;
+        };
+        let count = self.list[t].panes.len() as isize;
+        let p = (self.list[t].focus as isize + step).rem_euclid(count);
+        self.focus(t, p as usize);
+    }
+
+    fn focus(&mut self, t: usize, p: usize) {
+        let tab = &mut self.list[t];
+        tab.focus = p;
+        tab.panes[p].term.take_focus().ok();
+    }
+
+    fn exited(&mut self, id: u64, success: bool) {
+        let Some((t, p)) = self.list.iter().enumerate().find_map(|(t, tab)| {
+            let p = tab.panes.iter().position(|pane| pane.id == id)?;
+            Some((t, p))
+        }) else {
+            return;
+        };
+        // show which pane the question is about
+        self.tabs.set_value(&self.list[t].group).ok();
+        self.focus(t, p);
+        let msg = if success {
+            "The program exited."
+        } else {
+            "The program exited with an error."
+        };
+        match dialog::choice2_default(msg, "Close", "Restart", "") {
+            Some(1) => self.list[t].panes[p].term.restart(),
+            _ => self.close_pane(t, p),
+        }
+    }
+
+    // the index of the shown tab
+    fn current(&self) -> Option<usize> {
+        let group = self.tabs.value()?;
+        self.list
+            .iter()
+            .position(|tab| tab.group.as_widget_ptr() == group.as_widget_ptr())
+    }
+
+    // give a terminal the id its exit is reported to the channel with
+    fn pane(&mut self, mut term: AnsiTerm) -> Pane {
+        let id = self.next_id;
+        self.next_id += 1;
+        let sender = self.sender;
+        term.set_callback(move |_, status| sender.send(Message::Exited(id, status.success())));
+        Pane { id, term }
+    }
+}
+
+fltk::widget_extends!(Sessions, group::Tabs, tabs);
+
+// lay out the children of a group of panes again after adding or removing some
+fn relayout(group: &mut group::Group) {
+    group.resize(group.x(), group.y(), group.w(), group.h());
+    group.redraw();
+}
@@ -0,0 +1,964 @@
+use crate::cast::{Cast, Player, Recorder};
+use crate::vt::{Attrs, Color as VtColor, Modes, Terminal};
+use fltk::{enums::*, prelude::*, *};
+use portable_pty::{
+    native_pty_system, Child, ChildKiller, CommandBuilder, ExitStatus, MasterPty, PtySize,
+};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+const COLS: u16 = 80;
+const ROWS: u16 = 24;
+const FONT: Font = Font::Courier;
+const FONT_SIZE: i32 = 14;
+// FL_F, function key n being FN_KEY + n
+const FN_KEY: i32 = 0xffbd;
+// the colors of cells with the default colors
+const FOREGROUND: (u8, u8, u8) = (229, 229, 229);
+const BACKGROUND: (u8, u8, u8) = (0, 0, 0);
+// the backgrounds of selected cells, find matches and the current match
+const SELECTION: (u8, u8, u8) = (60, 90, 150);
+const MATCH: (u8, u8, u8) = (110, 90, 20);
+const CURRENT_MATCH: (u8, u8, u8) = (210, 120, 20);
+// the default number of lines kept above the screen
+const SCROLLBACK: usize = 1000;
+// the time between the frames of a playback, and the step of seeking it, in seconds
+const PLAYBACK_FRAME: f64 = 1. / 30.;
+const SEEK_STEP: f64 = 5.;
+// the lines scrolled by a step of the mouse wheel
+const WHEEL_LINES: isize = 3;
+
+type Callback = Rc<RefCell<Option<Box<dyn FnMut(&mut AnsiTerm, ExitStatus)>>>>;
+
+/// A terminal widget running a shell, drawing the screen of a VT100/xterm emulator
+#[derive(Clone)]
+pub struct AnsiTerm {
+    frame: frame::Frame,
+    vt: Arc<Mutex<Terminal>>,
+    // the PTY of the running program, None if it couldn't be started
+    pty: Rc<RefCell<Option<Pty>>>,
+    builder: Rc<AnsiTermBuilder>,
+    // the exit status of the program, set by the reading thread
+    exit: Arc<Mutex<Option<ExitStatus>>>,
+    // the output is written to it while recording
+    recorder: Arc<Mutex<Option<Recorder>>>,
+    // replaying a recording instead of running a program, on a timer
+    player: Rc<RefCell<Option<Player>>>,
+    timer: Rc<Cell<Option<app::TimeoutHandle>>>,
+    cb: Callback,
+}
+
+// the PTY a program runs in, replaced when the program is restarted
+struct Pty {
+    master: Box<dyn MasterPty + Send>,
+    writer: Writer,
+    // the reading thread owns the child, and a copy of the master which keeps the PTY open
+    killer: Box<dyn ChildKiller + Send + Sync>,
+}
+
+// the writing end of a PTY, shared with the thread answering status requests.
+// once the program exited, writes are dropped
+#[derive(Clone)]
+struct Writer {
+    inner: Arc<Mutex<Box<dyn Write + Send>>>,
+    open: Arc<AtomicBool>,
+}
+
+impl Writer {
+    // write to the program, failures being those of a program which is exiting
+    fn send(&self, bytes: &[u8]) {
+        if self.open.load(Ordering::Relaxed) {
+            let mut inner = self.inner.lock().unwrap();
+            inner.write_all(bytes).and_then(|_| inner.flush()).ok();
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.open.load(Ordering::Relaxed)
+    }
+
+    // stop writing, returns whether the writer was open
+    fn close(&self) -> bool {
+        self.open.swap(false, Ordering::Relaxed)
+    }
+}
+
+impl Default for AnsiTerm {
+    fn default() -> Self {
+        AnsiTerm::new(0, 0, 0, 0, None)
+    }
+}
+
+/// Sets up the program an `AnsiTerm` runs, an interactive shell by default
+#[derive(Debug, Clone)]
+pub struct AnsiTermBuilder {
+    shell: Option<String>,
+    args: Vec<String>,
+    cwd: Option<PathBuf>,
+    env: Vec<(String, String)>,
+    scrollback: usize,
+}
+
+impl AnsiTermBuilder {
+    /// Sets the program to run instead of the default shell
+    pub fn shell(mut self, program: &str) -> Self {
+        self.shell = Some(program.to_string());
+        self
+    }
+
+    /// Adds arguments of the program
+    pub fn args<I: IntoIterator<Item = S>, S: AsRef<str>>(mut self, args: I) -> Self {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_string()));
+        self
+    }
+
+    /// Sets the working directory of the program
+    pub fn cwd<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cwd = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    /// Sets an environment variable of the program
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Sets the number of lines kept above the screen
+    pub fn scrollback(mut self, lines: usize) -> Self {
+        self.scrollback = lines;
+        self
+    }
+
+    /// Creates the terminal and starts the program, the terminal is sized like other widgets
+    pub fn build(self) -> AnsiTerm {
+        let s = AnsiTerm::spawn(0, 0, 0, 0, None, &self);
+        s.start();
+        s
+    }
+
+    fn command(&self) -> CommandBuilder {
+        let mut cmd = match &self.shell {
+            Some(shell) => CommandBuilder::new(shell),
+            None if cfg!(target_os = "windows") => CommandBuilder::new("cmd.exe"),
+            None => {
+                let mut cmd = CommandBuilder::new("/bin/bash");
+                if self.args.is_empty() {
+                    cmd.arg("-i");
+                }
+                cmd
+            }
+        };
+        cmd.args(&self.args);
+        if let Some(dir) = &self.cwd {
+            cmd.cwd(dir);
+        }
+        cmd.env("TERM", "xterm-256color");
+        for (key, value) in &self.env {
+            cmd.env(key, value);
+        }
+        cmd
+    }
+}
+
+impl AnsiTerm {
+    /// Creates a terminal running an interactive shell
+    pub fn new<L: Into<Option<&'static str>>>(x: i32, y: i32, w: i32, h: i32, label: L) -> Self {
+        let s = Self::spawn(x, y, w, h, label, &Self::builder());
+        s.start();
+        s
+    }
+
+    /// Creates a terminal replaying a recording, without running a program
+    pub fn replay(cast: Cast) -> Self {
+        let mut s = Self::spawn(0, 0, 0, 0, None, &Self::builder());
+        s.play(cast);
+        s
+    }
+
+    /// Sets up a terminal running another program than the shell
+    pub fn builder() -> AnsiTermBuilder {
+        let builder = AnsiTermBuilder {
+            shell: None,
+            args: vec![],
+            cwd: None,
+            env: vec![],
+            scrollback: SCROLLBACK,
+        };
+        // true colors are drawn, which programs only assume with COLORTERM set
+        builder.env("COLORTERM", "truecolor")
+    }
+
+    fn spawn<L: Into<Option<&'static str>>>(
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        label: L,
+        builder: &AnsiTermBuilder,
+    ) -> Self {
+        let mut frame = frame::Frame::new(x, y, w, h, label);
+        let (r, g, b) = BACKGROUND;
+        frame.set_color(Color::from_rgb(r, g, b));
+        let mut vt = Terminal::new(COLS as usize, ROWS as usize);
+        vt.set_scrollback_limit(builder.scrollback);
+        let view = Rc::new(RefCell::new(View::default()));
+        let s = Self {
+            frame: frame.clone(),
+            vt: Arc::new(Mutex::new(vt)),
+            pty: Rc::default(),
+            builder: Rc::new(builder.clone()),
+            exit: Arc::default(),
+            recorder: Arc::default(),
+            player: Rc::default(),
+            timer: Rc::default(),
+            cb: Rc::default(),
+        };
+
+        frame.draw({
+            let vt = s.vt.clone();
+            let recorder = s.recorder.clone();
+            let player = s.player.clone();
+            let view = view.clone();
+            move |f| {
+                let vt = vt.lock().unwrap();
+                let recording = recorder.lock().unwrap().is_some();
+                draw_screen(f, &vt, &view.borrow(), player.borrow().as_ref(), recording);
+            }
+        });
+
+        frame.resize_callback({
+            let s = s.clone();
+            move |_, _, _, w, h| s.fit(w, h)
+        });
+
+        // the reading thread wakes the UI with this callback when the program exits
+        frame.set_callback({
+            let mut s = s.clone();
+            move |_| s.exited()
+        });
+
+        let vt = s.vt.clone();
+        let pty = s.pty.clone();
+        let player = s.player.clone();
+        // write to the program if it's running
+        let send = move |bytes: &[u8]| {
+            if let Some(pty) = pty.borrow().as_ref() {
+                pty.writer.send(bytes);
+            }
+        };
+        frame.handle(move |f, ev| {
+            let mut view = view.borrow_mut();
+            let (mx, my) = app::event_coords();
+            match ev {
+                Event::Focus | Event::Unfocus => {
+                    f.redraw();
+                    true
+                }
+                Event::Push => {
+                    f.take_focus().ok();
+                    match app::event_mouse_button() {
+                        app::MouseButton::Left => {
+                            let pos = view.cell_at(&vt.lock().unwrap(), f, mx, my);
+                            view.selection = Some((pos, pos));
+                        }
+                        app::MouseButton::Middle => app::paste(f),
+                        _ => (),
+                    }
+                    f.redraw();
+                    true
+                }
+                Event::Drag => {
+                    let vt = vt.lock().unwrap();
+                    // scroll while selecting above or below the widget
+                    if my < f.y() {
+                        view.scroll(&vt, -1);
+                    } else if my >= f.y() + f.h() {
+                        view.scroll(&vt, 1);
+                    }
+                    let pos = view.cell_at(&vt, f, mx, my);
+                    if let Some((start, _)) = view.selection {
+                        view.selection = Some((start, pos));
+                        f.redraw();
+                    }
+                    true
+                }
+                Event::Released => {
+                    // a selection is copied right away, a click clears it
+                    match view.selected_range() {
+                        Some((start, end)) if start != end => {
+                            app::copy(&vt.lock().unwrap().text_range(start, end));
+                        }
+                        _ => view.selection = None,
+                    }
+                    f.redraw();
+                    true
+                }
+                Event::MouseWheel => {
+                    let lines = match app::event_dy() {
+                        app::MouseWheel::Up => -WHEEL_LINES,
+                        app::MouseWheel::Down => WHEEL_LINES,
+                        _ => return false,
+                    };
+                    view.scroll(&vt.lock().unwrap(), lines);
+                    f.redraw();
+                    true
+                }
+                Event::Paste => {
+                    let text = app::event_text();
+                    if let Some(find) = view.find.as_mut() {
+                        find.text.push_str(text.lines().next().unwrap_or_default());
+                        view.refresh_find(&vt.lock().unwrap());
+                    } else {
+                        // terminals send pasted newlines as carriage returns
+                        let text = text.replace("\r\n", "\r").replace('\n', "\r");
+                        let text = if vt.lock().unwrap().modes().bracketed_paste {
+                            format!("\x1b[200~{}\x1b[201~", text)
+                        } else {
+                            text
+                        };
+                        send(text.as_bytes());
+                        view.top = None;
+                    }
+                    f.redraw();
+                    true
+                }
+                Event::KeyDown => {
+                    let mut vt = vt.lock().unwrap();
+                    let key = app::event_key();
+                    let state = app::event_state();
+                    let shift = state.contains(Shortcut::Shift);
+                    let ctrl_shift = shift && state.contains(Shortcut::Ctrl);
+                    if view.find.is_some() {
+                        view.find_key(&vt, key, &app::event_text(), state);
+                    } else if ctrl_shift && key == Key::from_char('f') {
+                        view.find = Some(Find::default());
+                    } else if ctrl_shift && key == Key::from_char('v') {
+                        app::paste(f);
+                    } else if ctrl_shift && key.bits() < 0x80 {
+                        // the other Ctrl+Shift shortcuts are left to the application
+                        return false;
+                    } else if let Some(player) = player.borrow_mut().as_mut() {
+                        // without a program, keys control the playback
+                        let time = player.time();
+                        match app::event_text().as_str() {
+                            " " => player.toggle_pause(&mut vt),
+                            "+" => player.change_speed(2.),
+                            "-" => player.change_speed(0.5),
+                            _ if key == Key::Left => player.seek(&mut vt, time - SEEK_STEP),
+                            _ if key == Key::Right => player.seek(&mut vt, time + SEEK_STEP),
+                            _ if key == Key::Home => player.seek(&mut vt, 0.),
+                            _ => return false,
+                        }
+                        view.top = None;
+                    } else if shift && key == Key::PageUp {
+                        view.scroll(&vt, 1 - vt.rows() as isize);
+                    } else if shift && key == Key::PageDown {
+                        view.scroll(&vt, vt.rows() as isize - 1);
+                    } else {
+                        // every other key goes to the program, which does the echoing.
+                        // a blocked write mustn't hold the screen from the reading thread
+                        let bytes = encode_key(key, &app::event_text(), state, vt.modes());
+                        drop(vt);
+                        match bytes {
+                            Some(bytes) => send(&bytes),
+                            None => return false,
+                        }
+                        view.top = None;
+                    }
+                    f.redraw();
+                    true
+                }
+                _ => false,
+            }
+        });
+        s
+    }
+
+    /// Starts the program again on a cleared screen, e.g. after it exited
+    pub fn restart(&mut self) {
+        self.kill();
+        self.exit.lock().unwrap().take();
+        {
+            let mut vt = self.vt.lock().unwrap();
+            *vt = Terminal::new(vt.cols(), vt.rows());
+            vt.set_scrollback_limit(self.builder.scrollback);
+        }
+        self.start();
+        self.frame.redraw();
+    }
+
+    /// Ends the program without reporting its exit, or the playback, e.g. before deleting
+    /// the terminal
+    pub fn kill(&mut self) {
+        if let Some(timer) = self.timer.take() {
+            app::remove_timeout3(timer);
+        }
+        self.player.borrow_mut().take();
+        // the reading thread checks the writer under this lock before touching the widget
+        let _vt = self.vt.lock().unwrap();
+        // the reading thread then sees the PTY end and exits without reporting
+        if let Some(mut pty) = self.pty.borrow_mut().take() {
+            pty.writer.close();
+            // fails when the program already exited
+            pty.killer.kill().ok();
+        }
+    }
+
+    /// Replays a recording in place of the program, which is ended.
+    /// Space pauses, the arrows seek and + and - change the speed
+    pub fn play(&mut self, cast: Cast) {
+        self.kill();
+        self.exit.lock().unwrap().take();
+        let mut player = Player::new(cast, self.builder.scrollback);
+        player.rewind(&mut self.vt.lock().unwrap());
+        *self.player.borrow_mut() = Some(player);
+        let mut frame = self.frame.clone();
+        let vt = self.vt.clone();
+        let player = self.player.clone();
+        let mut last = Instant::now();
+        let timer = app::add_timeout3(PLAYBACK_FRAME, move |handle| {
+            let now = Instant::now();
+            if let Some(player) = player.borrow_mut().as_mut() {
+                player.advance(&mut vt.lock().unwrap(), (now - last).as_secs_f64());
+            }
+            last = now;
+            frame.redraw();
+            app::repeat_timeout3(PLAYBACK_FRAME, handle);
+        });
+        self.timer.set(Some(timer));
+        self.frame.redraw();
+    }
+
+    /// Records the output of the program to an asciicast v2 file, until stopped
+    pub fn record<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let (cols, rows) = {
+            let vt = self.vt.lock().unwrap();
+            (vt.cols(), vt.rows())
+        };
+        *self.recorder.lock().unwrap() = Some(Recorder::create(path, cols, rows)?);
+        self.frame.redraw();
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        self.recorder.lock().unwrap().take();
+        self.frame.redraw();
+    }
+
+    /// The window title set by the program, empty if it set none
+    pub fn title(&self) -> String {
+        self.vt.lock().unwrap().title().to_string()
+    }
+
+    /// Set a callback which is triggered when the program exits, with its exit status.
+    /// The terminal can then be restarted or closed
+    pub fn set_callback<F: FnMut(&mut Self, ExitStatus) + 'static>(&mut self, cb: F) {
+        *self.cb.borrow_mut() = Some(Box::new(cb));
+    }
+
+    // keep the grid and the PTY the size of the widget, which signals the program.
+    // a playback keeps the recorded size
+    fn fit(&self, w: i32, h: i32) {
+        if self.player.borrow().is_some() {
+            return;
+        }
+        let (cw, ch) = cell_size();
+        let cols = (w / cw).clamp(1, u16::MAX as i32) as u16;
+        let rows = (h / ch).clamp(1, u16::MAX as i32) as u16;
+        let size = (cols as usize, rows as usize);
+        {
+            let mut vt = self.vt.lock().unwrap();
+            if size == (vt.cols(), vt.rows()) {
+                return;
+            }
+            vt.resize(size.0, size.1);
+        }
+        if let Some(rec) = self.recorder.lock().unwrap().as_mut() {
+            rec.resize(size.0, size.1).ok();
+        }
+        if let Some(pty) = self.pty.borrow().as_ref() {
+            pty.master
+                .resize(PtySize {
+                    cols,
+                    rows,
+                    pixel_width: cols.saturating_mul(cw as u16),
+                    pixel_height: rows.saturating_mul(ch as u16),
+                })
+                .ok();
+        }
+    }
+
+    // open a PTY the size of the widget and start the program in it,
+    // its output being read on a thread until it exits
+    fn start(&self) {
+        self.fit(self.frame.w(), self.frame.h());
+        let (cols, rows) = {
+            let vt = self.vt.lock().unwrap();
+            (vt.cols() as u16, vt.rows() as u16)
+        };
+        let (pty, mut child, mut reader) = match open_pty(&self.builder, cols, rows) {
+            Ok(opened) => opened,
+            Err(e) => {
+                let msg = format!("Couldn't start the program: {}\r\n", e);
+                self.vt.lock().unwrap().feed(msg.as_bytes());
+                return;
+            }
+        };
+        std::thread::spawn({
+            let mut frame = self.frame.clone();
+            let vt = self.vt.clone();
+            let writer = pty.writer.clone();
+            let exit = self.exit.clone();
+            let recorder = self.recorder.clone();
+            move || {
+                let mut buf = [0u8; 4096];
+                // reads block until there's output, and fail once the program exited
+                while let Ok(n @ 1..) = reader.read(&mut buf) {
+                    let mut vt = vt.lock().unwrap();
+                    // killed, the widget may be gone already
+                    if !writer.is_open() {
+                        return;
+                    }
+                    vt.feed(&buf[..n]);
+                    let mut recorder = recorder.lock().unwrap();
+                    // a recording which fails to be written is stopped
+                    if let Some(Err(_)) = recorder.as_mut().map(|rec| rec.output(&buf[..n])) {
+                        recorder.take();
+                    }
+                    // answer the status requests, e.g. the cursor position
+                    let replies = vt.take_replies();
+                    if !replies.is_empty() {
+                        writer.send(&replies);
+                    }
+                    frame.redraw();
+                    app::awake();
+                }
+                let status = child
+                    .wait()
+                    .unwrap_or_else(|_| ExitStatus::with_exit_code(1));
+                // a restart closes the writer first, the program it replaced isn't reported
+                if writer.close() {
+                    *exit.lock().unwrap() = Some(status);
+                    app::awake_callback(move || {
+                        if !frame.was_deleted() {
+                            frame.do_callback();
+                        }
+                    });
+                }
+            }
+        });
+        *self.pty.borrow_mut() = Some(pty);
+    }
+
+    fn exited(&mut self) {
+        let Some(status) = self.exit.lock().unwrap().take() else {
+            return;
+        };
+        if let Some(cb) = self.cb.clone().borrow_mut().as_mut() {
+            cb(self, status);
+        }
+    }
+}
+
+type Opened = (Pty, Box<dyn Child + Send + Sync>, Box<dyn Read + Send>);
+
+// open a PTY and start the program of the builder in it
+fn open_pty(builder: &AnsiTermBuilder, cols: u16, rows: u16) -> Result<Opened, String> {
+    let pair = native_pty_system()
+        .openpty(PtySize {
+            cols,
+            rows,
+            pixel_width: cols.saturating_mul(10),
+            pixel_height: rows.saturating_mul(16),
+        })
+        .map_err(|e| e.to_string())?;
+    let child = pair
+        .slave
+        .spawn_command(builder.command())
+        .map_err(|e| e.to_string())?;
+    let killer = child.clone_killer();
+    let reader = pair.master.try_clone_reader().map_err(|e| e.to_string())?;
+    let writer = pair.master.try_clone_writer().map_err(|e| e.to_string())?;
+    let pty = Pty {
+        master: pair.master,
+        writer: Writer {
+            inner: Arc::new(Mutex::new(writer)),
+            open: Arc::new(AtomicBool::new(true)),
+        },
+        killer,
+    };
+    Ok((pty, child, reader))
+}
+
+fltk::widget_extends!(AnsiTerm, frame::Frame, frame);
+
+// what's shown of the terminal besides its screen
+#[derive(Default)]
+struct View {
+    // the number of the line at the top when scrolled back, None following the output
+    top: Option<usize>,
+    // the (line, column) where the selection started and where it ends
+    selection: Option<((usize, usize), (usize, usize))>,
+    find: Option<Find>,
+}
+
+// an incremental search, its matches being (line, column, length)
+#[derive(Default)]
+struct Find {
+    text: String,
+    matches: Vec<(usize, usize, usize)>,
+    current: usize,
+}
+
+impl View {
+    // the number of the line at the top of the widget
+    fn top(&self, vt: &Terminal) -> usize {
+        match self.top {
+            Some(top) if !vt.alt_screen() => top.clamp(vt.first_line(), vt.screen_top()),
+            _ => vt.screen_top(),
+        }
+    }
+
+    // scroll by a number of lines, back into the scrollback when negative
+    fn scroll(&mut self, vt: &Terminal, lines: isize) {
+        let top = self
+            .top(vt)
+            .saturating_add_signed(lines)
+            .max(vt.first_line());
+        self.top = (top < vt.screen_top()).then_some(top);
+    }
+
+    // the (line, column) of the cell under a point of the widget
+    fn cell_at(&self, vt: &Terminal, f: &frame::Frame, x: i32, y: i32) -> (usize, usize) {
+        let (cw, ch) = cell_size();
+        let col = ((x - f.x()).max(0) / cw) as usize;
+        let row = ((y - f.y()).max(0) / ch) as usize;
+        (
+            self.top(vt) + row.min(vt.rows() - 1),
+            col.min(vt.cols() - 1),
+        )
+    }
+
+    // the start and end of the selection, in the order they're shown
+    fn selected_range(&self) -> Option<((usize, usize), (usize, usize))> {
+        self.selection
+            .map(|(a, b)| if a <= b { (a, b) } else { (b, a) })
+    }
+
+    // handle a key typed in the find bar. Enter goes to the previous match, up the output,
+    // and Shift+Enter to the next one
+    fn find_key(&mut self, vt: &Terminal, key: Key, text: &str, state: Shortcut) {
+        let Some(find) = self.find.as_mut() else {
+            return;
+        };
+        match key {
+            Key::Escape => self.find = None,
+            Key::Enter | Key::KPEnter if !find.matches.is_empty() => {
+                let len = find.matches.len();
+                find.current = if state.contains(Shortcut::Shift) {
+                    (find.current + 1) % len
+                } else {
+                    (find.current + len - 1) % len
+                };
+                self.show_match(vt);
+            }
+            Key::BackSpace => {
+                find.text.pop();
+                self.refresh_find(vt);
+            }
+            _ if !text.is_empty()
+                && !state.contains(Shortcut::Ctrl)
+                && !text.chars().any(char::is_control) =>
+            {
+                find.text.push_str(text);
+                self.refresh_find(vt);
+            }
+            _ => (),
+        }
+    }
+
+    // search again, going to the match closest to the end of the output
+    fn refresh_find(&mut self, vt: &Terminal) {
+        if let Some(find) = self.find.as_mut() {
+            find.matches = vt.find(&find.text);
+            find.current = find.matches.len().saturating_sub(1);
+        }
+        self.show_match(vt);
+    }
+
+    // scroll the current match into view
+    fn show_match(&mut self, vt: &Terminal) {
+        let Some(&(line, _, _)) = self.find.as_ref().and_then(|f| f.matches.get(f.current)) else {
+            return;
+        };
+        let top = self.top(vt);
+        if line < top || line >= top + vt.rows() {
+            let top = line.saturating_sub(vt.rows() / 2);
+            self.top = (top < vt.screen_top()).then_some(top);
+        }
+    }
+
+    // the background of a cell which is selected or matches the search
+    fn highlight(&self, line: usize, col: usize) -> Option<(u8, u8, u8)> {
+        if let Some((start, end)) = self.selected_range() {
+            if start <= (line, col) && (line, col) <= end {
+                return Some(SELECTION);
+            }
+        }
+        let find = self.find.as_ref()?;
+        // the matches are sorted, the first one on the line is looked up
+        let first = find.matches.partition_point(|&(l, _, _)| l < line);
+        let idx = (first..find.matches.len())
+            .take_while(|&i| find.matches[i].0 == line)
+            .find(|&i| {
+                let (_, c, len) = find.matches[i];
+                (c..c + len).contains(&col)
+            })?;
+        Some(if idx == find.current {
+            CURRENT_MATCH
+        } else {
+            MATCH
+        })
+    }
+}
+
+// the bytes a key sends to the program, encoded as xterm does
+fn encode_key(key: Key, text: &str, state: Shortcut, modes: &Modes) -> Option<Vec<u8>> {
+    let shift = state.contains(Shortcut::Shift);
+    let alt = state.contains(Shortcut::Alt);
+    let ctrl = state.contains(Shortcut::Ctrl);
+    // the xterm modifier parameter, 1 being no modifier
+    let mods = 1 + shift as u8 + 2 * alt as u8 + 4 * ctrl as u8;
+    // cursor keys send SS3 sequences in application cursor mode, unless modified
+    let cursor = |c: char| match (mods, modes.app_cursor) {
+        (1, true) => format!("\x1bO{}", c),
+        (1, false) => format!("\x1b[{}", c),
+        _ => format!("\x1b[1;{}{}", mods, c),
+    };
+    let function = |c: char| match mods {
+        1 => format!("\x1bO{}", c),
+        _ => format!("\x1b[1;{}{}", mods, c),
+    };
+    let tilde = |n: u8| match mods {
+        1 => format!("\x1b[{}~", n),
+        _ => format!("\x1b[{};{}~", n, mods),
+    };
+    let seq = match key {
+        Key::Up => cursor('A'),
+        Key::Down => cursor('B'),
+        Key::Right => cursor('C'),
+        Key::Left => cursor('D'),
+        Key::Home => cursor('H'),
+        Key::End => cursor('F'),
+        Key::Insert => tilde(2),
+        Key::Delete => tilde(3),
+        Key::PageUp => tilde(5),
+        Key::PageDown => tilde(6),
+        k if (1..=12).contains(&(k.bits() - FN_KEY)) => match k.bits() - FN_KEY {
+            n @ 1..=4 => function(b"PQRS"[n as usize - 1] as char),
+            n => tilde([15, 17, 18, 19, 20, 21, 23, 24][n as usize - 5]),
+        },
+        Key::Tab if shift => "\x1b[Z".to_string(),
+        Key::KPEnter if modes.app_keypad => "\x1bOM".to_string(),
+        _ => {
+            // the remaining keys send characters, Alt prefixing them with ESC as a meta key
+            let mut bytes = if alt { vec![0x1b] } else { vec![] };
+            match key {
+                Key::Enter | Key::KPEnter if modes.newline => bytes.extend_from_slice(b"\r\n"),
+                Key::Enter | Key::KPEnter => bytes.push(b'\r'),
+                Key::BackSpace if ctrl => bytes.push(0x08),
+                Key::BackSpace => bytes.push(0x7f),
+                Key::Tab => bytes.push(b'\t'),
+                Key::Escape => bytes.push(0x1b),
+                _ if ctrl => bytes.push(control_char(key)?),
+                _ if !text.is_empty() => bytes.extend_from_slice(text.as_bytes()),
+                _ => return None,
+            }
+            return Some(bytes);
+        }
+    };
+    Some(seq.into_bytes())
+}
+
+// the control character Ctrl and a key send, e.g. 0x03 for Ctrl+C
+fn control_char(key: Key) -> Option<u8> {
+    match u8::try_from(key.bits()).ok()? {
+        c @ b'a'..=b'z' => Some(c & 0x1f),
+        b' ' | b'2' | b'@' => Some(0),
+        b'[' | b'3' => Some(0x1b),
+        b'\\' | b'4' => Some(0x1c),
+        b']' | b'5' => Some(0x1d),
+        b'6' | b'^' => Some(0x1e),
+        b'-' | b'/' | b'7' => Some(0x1f),
+        b'8' | b'?' => Some(0x7f),
+        _ => None,
+    }
+}
+
+// the width and height of a cell in the terminal font
+fn cell_size() -> (i32, i32) {
+    draw::set_font(FONT, FONT_SIZE);
+    (draw::width("M").ceil() as i32, draw::height())
+}
+
+fn cell_font(attrs: &Attrs) -> Font {
+    match (attrs.bold, attrs.italic) {
+        (false, false) => FONT,
+        (true, false) => Font::CourierBold,
+        (false, true) => Font::CourierItalic,
+        (true, true) => Font::CourierBoldItalic,
+    }
+}
+
+// the foreground and background colors of a cell
+fn cell_colors(attrs: &Attrs, cursor: bool) -> (Color, Color) {
+    // bold ANSI colors are shown bright as in xterm
+    let fg = match attrs.fg {
+        VtColor::Indexed(idx) if attrs.bold && idx < 8 => VtColor::Indexed(idx + 8),
+        fg => fg,
+    };
+    let mut fg = fg.rgb(FOREGROUND);
+    let mut bg = attrs.bg.rgb(BACKGROUND);
+    if attrs.dim {
+        fg = (
+            fg.0 / 2 + bg.0 / 2,
+            fg.1 / 2 + bg.1 / 2,
+            fg.2 / 2 + bg.2 / 2,
+        );
+    }
+    if attrs.inverse != cursor {
+        std::mem::swap(&mut fg, &mut bg);
+    }
+    if attrs.hidden {
+        fg = bg;
+    }
+    (
+        Color::from_rgb(fg.0, fg.1, fg.2),
+        Color::from_rgb(bg.0, bg.1, bg.2),
+    )
+}
+
+fn draw_screen(
+    f: &frame::Frame,
+    vt: &Terminal,
+    view: &View,
+    player: Option<&Player>,
+    recording: bool,
+) {
+    draw::push_clip(f.x(), f.y(), f.w(), f.h());
+    draw::draw_rect_fill(f.x(), f.y(), f.w(), f.h(), f.color());
+    let (cw, ch) = cell_size();
+    let descent = draw::descent();
+    let top = view.top(vt);
+    let (crow, ccol) = vt.cursor();
+    let cursor_line = vt.screen_top() + crow;
+    let focused = f.has_focus();
+    let cursor_shown = vt.modes().cursor_visible;
+    for row in 0..vt.rows() {
+        let y = f.y() + row as i32 * ch;
+        let line = top + row;
+        for (col, cell) in vt.history_line(line).unwrap_or_default().iter().enumerate() {
+            let x = f.x() + col as i32 * cw;
+            // a block cursor when focused, an outline otherwise
+            let at_cursor = cursor_shown && line == cursor_line && col == ccol;
+            let (fg, mut bg) = cell_colors(&cell.attrs, at_cursor && focused);
+            if let Some((r, g, b)) = view.highlight(line, col) {
+                bg = Color::from_rgb(r, g, b);
+            }
+            if bg != f.color() {
+                draw::draw_rect_fill(x, y, cw, ch, bg);
+            }
+            if at_cursor && !focused {
+                draw::set_draw_color(fg);
+                draw::draw_rect(x, y, cw, ch);
+            }
+            if cell.ch == ' ' && !cell.attrs.underline && !cell.attrs.strike {
+                continue;
+            }
+            draw::set_draw_color(fg);
+            draw::set_font(cell_font(&cell.attrs), FONT_SIZE);
+            let base = y + ch - descent;
+            draw::draw_text(cell.ch.encode_utf8(&mut [0; 4]), x, base);
+            if cell.attrs.underline {
+                draw::draw_line(x, base + 1, x + cw - 1, base + 1);
+            }
+            if cell.attrs.strike {
+                draw::draw_line(x, y + ch / 2, x + cw - 1, y + ch / 2);
+            }
+        }
+    }
+    draw::set_font(FONT, FONT_SIZE);
+    // how far the view is scrolled back
+    if top < vt.screen_top() {
+        let label = format!("-{}", vt.screen_top() - top);
+        let w = draw::width(&label) as i32 + 8;
+        draw::draw_rect_fill(f.x() + f.w() - w, f.y(), w, ch, Color::from_rgb(60, 60, 60));
+        draw::set_draw_color(Color::White);
+        draw::draw_text2(&label, f.x() + f.w() - w, f.y(), w, ch, Align::Center);
+    }
+    if let Some(find) = &view.find {
+        let status = match find.matches.len() {
+            0 if find.text.is_empty() => String::new(),
+            0 => "no matches".to_string(),
+            n => format!("{}/{}", find.current + 1, n),
+        };
+        let y = f.y() + f.h() - ch - 4;
+        draw::draw_rect_fill(f.x(), y, f.w(), ch + 4, Color::from_rgb(50, 50, 50));
+        draw::set_draw_color(Color::White);
+        draw::draw_text2(
+            &format!("Find: {}_", find.text),
+            f.x() + 4,
+            y,
+            f.w() - 8,
+            ch + 4,
+            Align::Left,
+        );
+        draw::draw_text2(&status, f.x() + 4, y, f.w() - 8, ch + 4, Align::Right);
+    } else if let Some(player) = player {
+        // the position of the playback over its progress
+        let y = f.y() + f.h() - ch - 4;
+        let duration = player.duration();
+        let progress = if duration > 0. {
+            player.time() / duration
+        } else {
+            1.
+        };
+        draw::draw_rect_fill(f.x(), y, f.w(), ch + 4, Color::from_rgb(50, 50, 50));
+        let done = (f.w() as f64 * progress) as i32;
+        draw::draw_rect_fill(f.x(), y, done, ch + 4, Color::from_rgb(70, 80, 120));
+        let state = if player.paused() { "Paused" } else { "Playing" };
+        let label = format!(
+            "{} {} / {}  x{}",
+            state,
+            clock(player.time()),
+            clock(duration),
+            player.speed()
+        );
+        draw::set_draw_color(Color::White);
+        draw::draw_text2(&label, f.x() + 4, y, f.w() - 8, ch + 4, Align::Left);
+        let keys = "Space pause  Left/Right seek  +/- speed";
+        draw::draw_text2(keys, f.x() + 4, y, f.w() - 8, ch + 4, Align::Right);
+    }
+    if recording {
+        let w = draw::width("REC") as i32 + 8;
+        draw::draw_rect_fill(f.x(), f.y(), w, ch, Color::from_rgb(170, 30, 30));
+        draw::set_draw_color(Color::White);
+        draw::draw_text2("REC", f.x(), f.y(), w, ch, Align::Center);
+    }
+    draw::pop_clip();
+}
+
+// minutes and seconds of a time in seconds
+fn clock(secs: f64) -> String {
+    let secs = secs as u64;
+    format!("{}:{:02}", secs / 60, secs % 60)
+}
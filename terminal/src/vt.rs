@@ -0,0 +1,1217 @@
+use std::collections::VecDeque;
+
+// the longest CSI or OSC sequence kept, longer ones are cut off
+const MAX_SEQUENCE: usize = 4096;
+// the distance between the default tab stops
+const TAB_WIDTH: usize = 8;
+
+/// The color of a cell, Default being the terminal's foreground or background color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Color {
+    #[default]
+    Default,
+    /// One of the 256 xterm colors, the first 16 being the ANSI colors
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    /// The RGB value of the color, given the default color
+    pub fn rgb(&self, default: (u8, u8, u8)) -> (u8, u8, u8) {
+        match *self {
+            Color::Default => default,
+            Color::Indexed(idx) => palette(idx),
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+/// The RGB value of one of the 256 xterm colors
+pub fn palette(idx: u8) -> (u8, u8, u8) {
+    const ANSI: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 49, 49),
+        (13, 188, 121),
+        (229, 229, 16),
+        (36, 114, 200),
+        (188, 63, 188),
+        (17, 168, 205),
+        (229, 229, 229),
+        (102, 102, 102),
+        (241, 76, 76),
+        (35, 209, 139),
+        (245, 245, 67),
+        (59, 142, 234),
+        (214, 112, 214),
+        (41, 184, 219),
+        (255, 255, 255),
+    ];
+    match idx {
+        0..=15 => ANSI[idx as usize],
+        16..=231 => {
+            let i = idx - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            (level(i / 36), level(i / 6 % 6), level(i % 6))
+        }
+        _ => {
+            let v = 8 + (idx - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+/// The rendition of a cell, as set by SGR sequences
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Attrs {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+    pub hidden: bool,
+    pub strike: bool,
+}
+
+/// A character of the grid with its rendition
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub attrs: Attrs,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            ch: ' ',
+            attrs: Attrs::default(),
+        }
+    }
+}
+
+/// The modes set by the application running in the terminal
+#[derive(Debug, Clone, Copy)]
+pub struct Modes {
+    /// DECCKM, the cursor keys send SS3 rather than CSI sequences
+    pub app_cursor: bool,
+    /// DECKPAM, the keypad sends application sequences
+    pub app_keypad: bool,
+    /// DECAWM, writing past the last column wraps to the next line
+    pub autowrap: bool,
+    /// DECOM, cursor positions are relative to the scroll region
+    pub origin: bool,
+    /// IRM, written characters shift the rest of the line right
+    pub insert: bool,
+    /// LNM, a line feed also returns the carriage
+    pub newline: bool,
+    /// DECTCEM, the cursor is shown
+    pub cursor_visible: bool,
+    /// Pasted text is wrapped in ESC [200~ and ESC [201~
+    pub bracketed_paste: bool,
+}
+
+impl Default for Modes {
+    fn default() -> Self {
+        Self {
+            app_cursor: false,
+            app_keypad: false,
+            autowrap: true,
+            origin: false,
+            insert: false,
+            newline: false,
+            cursor_visible: true,
+            bracketed_paste: false,
+        }
+    }
+}
+
+// the cursor with what's saved along with it by DECSC
+#[derive(Debug, Clone, Copy, Default)]
+struct Cursor {
+    row: usize,
+    col: usize,
+    attrs: Attrs,
+    // a character was written in the last column, the next one wraps
+    wrap_pending: bool,
+    // whether G0 and G1 are the DEC line drawing set, and whether G1 is shifted in
+    g0_graphics: bool,
+    g1_graphics: bool,
+    shift_out: bool,
+    origin: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Ground,
+    Escape,
+    // an escape sequence with intermediate bytes, e.g. ESC ( 0
+    EscapeIntermediate(u8),
+    Csi,
+    Osc,
+    // a device control, privacy or application string, which is ignored
+    Ignored,
+    // an ESC within a string, the start of its terminator, true within an OSC string
+    StringEscape(bool),
+}
+
+/// A VT100/xterm terminal: a grid of cells written by feeding it the output of a program
+pub struct Terminal {
+    cols: usize,
+    rows: usize,
+    lines: Vec<Vec<Cell>>,
+    // the lines scrolled off the top of the primary screen, the oldest first
+    scrollback: VecDeque<Vec<Cell>>,
+    scrollback_limit: usize,
+    // the number of lines dropped from the scrollback, so lines keep their number
+    dropped: usize,
+    // the primary screen while the alternate one is shown, or the other way around
+    other_lines: Vec<Vec<Cell>>,
+    alt_screen: bool,
+    cursor: Cursor,
+    saved: Cursor,
+    other_saved: Cursor,
+    // the scroll region, inclusive
+    top: usize,
+    bottom: usize,
+    tabs: Vec<bool>,
+    modes: Modes,
+    title: String,
+    state: State,
+    seq: Vec<u8>,
+    utf8: Vec<u8>,
+    last_char: char,
+    replies: Vec<u8>,
+}
+
+impl Terminal {
+    /// Creates a blank terminal of the given size
+    pub fn new(cols: usize, rows: usize) -> Self {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        Self {
+            cols,
+            rows,
+            lines: vec![vec![Cell::default(); cols]; rows],
+            scrollback: VecDeque::new(),
+            scrollback_limit: 0,
+            dropped: 0,
+            other_lines: vec![vec![Cell::default(); cols]; rows],
+            alt_screen: false,
+            cursor: Cursor::default(),
+            saved: Cursor::default(),
+            other_saved: Cursor::default(),
+            top: 0,
+            bottom: rows - 1,
+            tabs: (0..cols).map(|c| c % TAB_WIDTH == 0).collect(),
+            modes: Modes::default(),
+            title: String::new(),
+            state: State::Ground,
+            seq: vec![],
+            utf8: vec![],
+            last_char: ' ',
+            replies: vec![],
+        }
+    }
+
+    /// The number of columns
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// The number of rows
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Sets the number of lines kept above the screen, none by default
+    pub fn set_scrollback_limit(&mut self, lines: usize) {
+        self.scrollback_limit = lines;
+        self.trim_scrollback();
+    }
+
+    /// The number of the oldest line kept. Lines are numbered from the first one written,
+    /// the scrollback coming before the screen
+    pub fn first_line(&self) -> usize {
+        self.dropped
+    }
+
+    /// The number of the top line of the screen
+    pub fn screen_top(&self) -> usize {
+        self.dropped + self.scrollback.len()
+    }
+
+    /// A line of the scrollback or the screen by its number, None if it isn't kept
+    pub fn history_line(&self, n: usize) -> Option<&[Cell]> {
+        let idx = n.checked_sub(self.dropped)?;
+        match self.scrollback.get(idx) {
+            Some(line) => Some(line),
+            None => self
+                .lines
+                .get(idx - self.scrollback.len())
+                .map(|l| l.as_slice()),
+        }
+    }
+
+    /// The text from one (line, column) position to another, both included.
+    /// Trailing spaces are removed from each line
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        (start.0..=end.0)
+            .map(|n| {
+                let line = self.history_line(n).unwrap_or_default();
+                let from = if n == start.0 { start.1 } else { 0 };
+                let to = if n == end.0 { end.1 + 1 } else { line.len() };
+                line.iter()
+                    .take(to)
+                    .skip(from)
+                    .map(|c| c.ch)
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The matches of a text in the scrollback and the screen, ignoring case,
+    /// as (line, column, length)
+    pub fn find(&self, text: &str) -> Vec<(usize, usize, usize)> {
+        let lower = |c: char| c.to_lowercase().next().unwrap_or(c);
+        let needle: Vec<char> = text.chars().map(lower).collect();
+        if needle.is_empty() {
+            return vec![];
+        }
+        let mut matches = vec![];
+        for n in self.first_line()..self.screen_top() + self.rows {
+            let line: Vec<char> = self
+                .history_line(n)
+                .unwrap_or_default()
+                .iter()
+                .map(|c| lower(c.ch))
+                .collect();
+            for col in 0..(line.len() + 1).saturating_sub(needle.len()) {
+                if line[col..col + needle.len()] == needle[..] {
+                    matches.push((n, col, needle.len()));
+                }
+            }
+        }
+        matches
+    }
+
+    /// The row and column of the cursor
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.cursor.row, self.cursor.col)
+    }
+
+    /// Whether the alternate screen of full-screen programs is shown, which has no scrollback
+    pub fn alt_screen(&self) -> bool {
+        self.alt_screen
+    }
+
+    /// The modes set by the application
+    pub fn modes(&self) -> &Modes {
+        &self.modes
+    }
+
+    /// The window title set by the program with OSC 0 or 2, empty until then
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Takes the replies to status requests, which should be written back to the program
+    pub fn take_replies(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.replies)
+    }
+
+    /// Changes the size of the screen. Lines are cut off at the top when the cursor would
+    /// fall below the screen, otherwise at the bottom. Both screens lose the same lines
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        if rows < self.rows {
+            let above = (self.cursor.row + 1).saturating_sub(rows);
+            let cut: Vec<_> = self.lines.drain(..above).collect();
+            let other_cut: Vec<_> = self.other_lines.drain(..above).collect();
+            // the lines cut off the primary screen go to the scrollback, whichever is shown
+            let primary_cut = if self.alt_screen { other_cut } else { cut };
+            for line in primary_cut {
+                self.push_scrollback(line);
+            }
+            self.cursor.row -= above;
+            for cursor in [&mut self.saved, &mut self.other_saved] {
+                cursor.row = cursor.row.saturating_sub(above);
+            }
+        }
+        for lines in [&mut self.lines, &mut self.other_lines] {
+            lines.resize(rows, vec![Cell::default(); cols]);
+            for line in lines.iter_mut() {
+                line.resize(cols, Cell::default());
+            }
+        }
+        self.tabs = (0..cols)
+            .map(|c| self.tabs.get(c).copied().unwrap_or(c % TAB_WIDTH == 0))
+            .collect();
+        self.cols = cols;
+        self.rows = rows;
+        self.top = 0;
+        self.bottom = rows - 1;
+        for cursor in [&mut self.saved, &mut self.other_saved] {
+            cursor.row = cursor.row.min(rows - 1);
+            cursor.col = cursor.col.min(cols - 1);
+        }
+        self.move_to(self.cursor.row, self.cursor.col);
+    }
+
+    /// Feeds the output of the program to the terminal
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.byte(b);
+        }
+    }
+
+    fn byte(&mut self, b: u8) {
+        // CAN and SUB abort any sequence, ESC starts a new one except within strings
+        match (b, self.state) {
+            (0x18 | 0x1a, _) => {
+                self.state = State::Ground;
+                return;
+            }
+            (0x1b, State::Osc | State::Ignored) => {
+                self.state = State::StringEscape(self.state == State::Osc);
+                return;
+            }
+            (0x1b, _) => {
+                self.utf8.clear();
+                self.seq.clear();
+                self.state = State::Escape;
+                return;
+            }
+            _ => (),
+        }
+        match self.state {
+            State::Ground => self.ground(b),
+            State::Escape => self.escape(b),
+            State::EscapeIntermediate(i) => {
+                if b < 0x20 {
+                    self.control(b);
+                } else if (0x30..0x7f).contains(&b) {
+                    self.escape_intermediate(i, b);
+                    self.state = State::Ground;
+                }
+            }
+            State::Csi => {
+                if b < 0x20 {
+                    self.control(b);
+                } else if (0x40..0x7f).contains(&b) {
+                    self.csi(b);
+                    self.state = State::Ground;
+                } else if self.seq.len() < MAX_SEQUENCE {
+                    self.seq.push(b);
+                }
+            }
+            State::Osc => {
+                if b == 0x07 {
+                    self.osc();
+                    self.state = State::Ground;
+                } else if b >= 0x20 && self.seq.len() < MAX_SEQUENCE {
+                    self.seq.push(b);
+                }
+            }
+            State::Ignored => {
+                if b == 0x07 {
+                    self.state = State::Ground;
+                }
+            }
+            State::StringEscape(osc) => {
+                // ESC \ ends the string, anything else starts a new escape sequence
+                if b == b'\\' {
+                    if osc {
+                        self.osc();
+                    }
+                    self.state = State::Ground;
+                } else {
+                    self.seq.clear();
+                    self.state = State::Escape;
+                    self.escape(b);
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, b: u8) {
+        if b < 0x20 || b == 0x7f {
+            self.utf8.clear();
+            self.control(b);
+            return;
+        }
+        if b < 0x80 {
+            if !self.utf8.is_empty() {
+                self.utf8.clear();
+                self.print(char::REPLACEMENT_CHARACTER);
+            }
+            self.print(b as char);
+            return;
+        }
+        // a continuation byte without a lead byte, or a new lead byte in a sequence
+        let is_lead = b & 0xc0 == 0xc0;
+        if is_lead != self.utf8.is_empty() {
+            self.utf8.clear();
+            self.print(char::REPLACEMENT_CHARACTER);
+            if !is_lead {
+                return;
+            }
+        }
+        self.utf8.push(b);
+        let len = match self.utf8[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        if self.utf8.len() == len {
+            let ch = std::str::from_utf8(&self.utf8)
+                .ok()
+                .and_then(|s| s.chars().next())
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            self.utf8.clear();
+            self.print(ch);
+        }
+    }
+
+    fn control(&mut self, b: u8) {
+        match b {
+            0x08 => {
+                self.cursor.col = self.cursor.col.saturating_sub(1);
+                self.cursor.wrap_pending = false;
+            }
+            0x09 => self.tab_forward(1),
+            0x0a..=0x0c => {
+                if self.modes.newline {
+                    self.cursor.col = 0;
+                }
+                self.linefeed();
+            }
+            0x0d => {
+                self.cursor.col = 0;
+                self.cursor.wrap_pending = false;
+            }
+            0x0e => self.cursor.shift_out = true,
+            0x0f => self.cursor.shift_out = false,
+            _ => (),
+        }
+    }
+
+    fn escape(&mut self, b: u8) {
+        self.state = State::Ground;
+        match b {
+            b'[' => {
+                self.seq.clear();
+                self.state = State::Csi;
+            }
+            b']' => {
+                self.seq.clear();
+                self.state = State::Osc;
+            }
+            b'P' | b'X' | b'^' | b'_' => self.state = State::Ignored,
+            0x20..=0x2f => self.state = State::EscapeIntermediate(b),
+            b'7' => self.save_cursor(),
+            b'8' => self.restore_cursor(),
+            b'D' => self.linefeed(),
+            b'E' => {
+                self.cursor.col = 0;
+                self.linefeed();
+            }
+            b'M' => self.reverse_index(),
+            b'H' => self.tabs[self.cursor.col] = true,
+            b'=' => self.modes.app_keypad = true,
+            b'>' => self.modes.app_keypad = false,
+            b'c' => self.reset(),
+            _ if b < 0x20 => self.control(b),
+            _ => (),
+        }
+    }
+
+    fn escape_intermediate(&mut self, intermediate: u8, b: u8) {
+        match (intermediate, b) {
+            (b'(', _) => self.cursor.g0_graphics = b == b'0',
+            (b')', _) => self.cursor.g1_graphics = b == b'0',
+            // DECALN, fill the screen with Es
+            (b'#', b'8') => {
+                for line in &mut self.lines {
+                    line.fill(Cell {
+                        ch: 'E',
+                        attrs: Attrs::default(),
+                    });
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn osc(&mut self) {
+        let s = String::from_utf8_lossy(&self.seq).into_owned();
+        self.seq.clear();
+        if let Some((cmd, text)) = s.split_once(';') {
+            if cmd == "0" || cmd == "2" {
+                self.title = text.to_string();
+            }
+        }
+    }
+
+    fn csi(&mut self, fin: u8) {
+        let seq = std::mem::take(&mut self.seq);
+        let (private, rest) = match seq.first() {
+            Some(&p @ (b'?' | b'>' | b'<' | b'=')) => (Some(p), &seq[1..]),
+            _ => (None, &seq[..]),
+        };
+        let split = rest
+            .iter()
+            .position(|b| (0x20..0x30).contains(b))
+            .unwrap_or(rest.len());
+        let intermediate = rest.get(split).copied();
+        let params: Vec<Vec<u16>> = std::str::from_utf8(&rest[..split])
+            .unwrap_or_default()
+            .split(';')
+            .map(|p| {
+                p.split(':')
+                    .map(|v| v.parse::<u32>().unwrap_or(0).min(u16::MAX as u32) as u16)
+                    .collect()
+            })
+            .collect();
+        // the nth parameter, 0 or missing ones being the default
+        let arg = |n: usize, default: u16| -> usize {
+            match params.get(n).and_then(|p| p.first()) {
+                Some(&v) if v > 0 => v as usize,
+                _ => default as usize,
+            }
+        };
+        match (private, intermediate, fin) {
+            (None, None, b'@') => self.insert_chars(arg(0, 1)),
+            (None, None, b'A') => self.move_up(arg(0, 1)),
+            (None, None, b'B' | b'e') => self.move_down(arg(0, 1)),
+            (None, None, b'C' | b'a') => self.move_to(self.cursor.row, self.cursor.col + arg(0, 1)),
+            (None, None, b'D') => {
+                self.move_to(self.cursor.row, self.cursor.col.saturating_sub(arg(0, 1)))
+            }
+            (None, None, b'E') => {
+                self.move_down(arg(0, 1));
+                self.cursor.col = 0;
+            }
+            (None, None, b'F') => {
+                self.move_up(arg(0, 1));
+                self.cursor.col = 0;
+            }
+            (None, None, b'G' | b'`') => self.move_to(self.cursor.row, arg(0, 1) - 1),
+            (None, None, b'H' | b'f') => self.goto(arg(0, 1) - 1, arg(1, 1) - 1),
+            (None, None, b'I') => self.tab_forward(arg(0, 1)),
+            (None | Some(b'?'), None, b'J') => self.erase_display(arg(0, 0)),
+            (None | Some(b'?'), None, b'K') => self.erase_line(arg(0, 0)),
+            (None, None, b'L') => self.insert_lines(arg(0, 1)),
+            (None, None, b'M') => self.delete_lines(arg(0, 1)),
+            (None, None, b'P') => self.delete_chars(arg(0, 1)),
+            (None, None, b'S') => self.scroll_up(arg(0, 1)),
+            (None, None, b'T') => self.scroll_down(arg(0, 1)),
+            (None, None, b'X') => self.erase_chars(arg(0, 1)),
+            (None, None, b'Z') => self.tab_backward(arg(0, 1)),
+            (None, None, b'b') => {
+                for _ in 0..arg(0, 1).min(self.cols * self.rows) {
+                    self.print(self.last_char);
+                }
+            }
+            (None, None, b'c') => self.replies.extend_from_slice(b"\x1b[?62;22c"),
+            (Some(b'>'), None, b'c') => self.replies.extend_from_slice(b"\x1b[>1;10;0c"),
+            (None, None, b'd') => self.goto(arg(0, 1) - 1, self.cursor.col),
+            (None, None, b'g') => match arg(0, 0) {
+                0 => self.tabs[self.cursor.col] = false,
+                3 => self.tabs.fill(false),
+                _ => (),
+            },
+            (None, None, b'h') => self.set_modes(&params, true),
+            (None, None, b'l') => self.set_modes(&params, false),
+            (Some(b'?'), None, b'h') => self.set_private_modes(&params, true),
+            (Some(b'?'), None, b'l') => self.set_private_modes(&params, false),
+            (None, None, b'm') => self.sgr(&params),
+            (None, None, b'n') => match arg(0, 0) {
+                5 => self.replies.extend_from_slice(b"\x1b[0n"),
+                6 => {
+                    let row = if self.cursor.origin {
+                        self.cursor.row.saturating_sub(self.top)
+                    } else {
+                        self.cursor.row
+                    };
+                    let reply = format!("\x1b[{};{}R", row + 1, self.cursor.col + 1);
+                    self.replies.extend_from_slice(reply.as_bytes());
+                }
+                _ => (),
+            },
+            (None, None, b'r') => {
+                let top = arg(0, 1) - 1;
+                let bottom = arg(1, self.rows as u16).min(self.rows) - 1;
+                if top < bottom {
+                    self.top = top;
+                    self.bottom = bottom;
+                    self.goto(0, 0);
+                }
+            }
+            (None, None, b's') => self.save_cursor(),
+            (None, None, b'u') => self.restore_cursor(),
+            // DECSTR, a soft reset
+            (None, Some(b'!'), b'p') => {
+                self.modes = Modes::default();
+                self.cursor.attrs = Attrs::default();
+                self.top = 0;
+                self.bottom = self.rows - 1;
+            }
+            _ => (),
+        }
+    }
+
+    fn set_modes(&mut self, params: &[Vec<u16>], on: bool) {
+        for p in params.iter().filter_map(|p| p.first()) {
+            match p {
+                4 => self.modes.insert = on,
+                20 => self.modes.newline = on,
+                _ => (),
+            }
+        }
+    }
+
+    fn set_private_modes(&mut self, params: &[Vec<u16>], on: bool) {
+        for p in params.iter().filter_map(|p| p.first()) {
+            match p {
+                1 => self.modes.app_cursor = on,
+                6 => {
+                    self.modes.origin = on;
+                    self.cursor.origin = on;
+                    self.goto(0, 0);
+                }
+                7 => self.modes.autowrap = on,
+                25 => self.modes.cursor_visible = on,
+                47 | 1047 => self.switch_screen(on),
+                1048 => {
+                    if on {
+                        self.save_cursor();
+                    } else {
+                        self.restore_cursor();
+                    }
+                }
+                1049 => {
+                    if on {
+                        self.save_cursor();
+                        self.switch_screen(true);
+                        self.erase_display(2);
+                    } else {
+                        self.switch_screen(false);
+                        self.restore_cursor();
+                    }
+                }
+                2004 => self.modes.bracketed_paste = on,
+                _ => (),
+            }
+        }
+    }
+
+    fn sgr(&mut self, params: &[Vec<u16>]) {
+        let attrs = &mut self.cursor.attrs;
+        let mut i = 0;
+        while i < params.len() {
+            let p = &params[i];
+            match p.first().copied().unwrap_or(0) {
+                0 => *attrs = Attrs::default(),
+                1 => attrs.bold = true,
+                2 => attrs.dim = true,
+                3 => attrs.italic = true,
+                4 => attrs.underline = p.get(1) != Some(&0),
+                7 => attrs.inverse = true,
+                8 => attrs.hidden = true,
+                9 => attrs.strike = true,
+                21 => attrs.underline = true,
+                22 => {
+                    attrs.bold = false;
+                    attrs.dim = false;
+                }
+                23 => attrs.italic = false,
+                24 => attrs.underline = false,
+                27 => attrs.inverse = false,
+                28 => attrs.hidden = false,
+                29 => attrs.strike = false,
+                v @ 30..=37 => attrs.fg = Color::Indexed(v as u8 - 30),
+                v @ (38 | 48) => {
+                    // either 38:5:n and 38:2::r:g:b, or 38;5;n and 38;2;r;g;b
+                    let (color, used) = if p.len() > 1 {
+                        (extended_color(&p[1..], true), 0)
+                    } else {
+                        let rest: Vec<u16> = params[i + 1..]
+                            .iter()
+                            .map(|p| p.first().copied().unwrap_or(0))
+                            .collect();
+                        let used = match rest.first() {
+                            Some(5) => 2,
+                            Some(2) => 4,
+                            _ => rest.len(),
+                        };
+                        (extended_color(&rest, false), used.min(rest.len()))
+                    };
+                    if let Some(color) = color {
+                        if v == 38 {
+                            attrs.fg = color;
+                        } else {
+                            attrs.bg = color;
+                        }
+                    }
+                    i += used;
+                }
+                39 => attrs.fg = Color::Default,
+                v @ 40..=47 => attrs.bg = Color::Indexed(v as u8 - 40),
+                49 => attrs.bg = Color::Default,
+                v @ 90..=97 => attrs.fg = Color::Indexed(v as u8 - 90 + 8),
+                v @ 100..=107 => attrs.bg = Color::Indexed(v as u8 - 100 + 8),
+                _ => (),
+            }
+            i += 1;
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        let graphics = if self.cursor.shift_out {
+            self.cursor.g1_graphics
+        } else {
+            self.cursor.g0_graphics
+        };
+        let ch = if graphics { line_drawing(ch) } else { ch };
+        if self.cursor.wrap_pending {
+            self.cursor.col = 0;
+            self.linefeed();
+        }
+        let (row, col) = (self.cursor.row, self.cursor.col);
+        if self.modes.insert {
+            let line = &mut self.lines[row];
+            line.pop();
+            line.insert(col, Cell::default());
+        }
+        self.lines[row][col] = Cell {
+            ch,
+            attrs: self.cursor.attrs,
+        };
+        self.last_char = ch;
+        if col + 1 < self.cols {
+            self.cursor.col += 1;
+        } else {
+            self.cursor.wrap_pending = self.modes.autowrap;
+        }
+    }
+
+    // a blank cell, keeping the background color as xterm does
+    fn blank(&self) -> Cell {
+        Cell {
+            ch: ' ',
+            attrs: Attrs {
+                bg: self.cursor.attrs.bg,
+                ..Attrs::default()
+            },
+        }
+    }
+
+    fn blank_line(&self) -> Vec<Cell> {
+        vec![self.blank(); self.cols]
+    }
+
+    fn linefeed(&mut self) {
+        self.cursor.wrap_pending = false;
+        if self.cursor.row == self.bottom {
+            self.scroll_up(1);
+        } else if self.cursor.row + 1 < self.rows {
+            self.cursor.row += 1;
+        }
+    }
+
+    fn reverse_index(&mut self) {
+        self.cursor.wrap_pending = false;
+        if self.cursor.row == self.top {
+            self.scroll_down(1);
+        } else {
+            self.cursor.row = self.cursor.row.saturating_sub(1);
+        }
+    }
+
+    // scroll the scroll region up, blank lines coming in at its bottom
+    fn scroll_up(&mut self, n: usize) {
+        let n = n.min(self.bottom - self.top + 1);
+        for _ in 0..n {
+            let line = self.lines.remove(self.top);
+            let blank = self.blank_line();
+            self.lines.insert(self.bottom, blank);
+            // lines leaving the top of the primary screen go to the scrollback
+            if self.top == 0 && !self.alt_screen {
+                self.push_scrollback(line);
+            }
+        }
+    }
+
+    fn push_scrollback(&mut self, line: Vec<Cell>) {
+        self.scrollback.push_back(line);
+        self.trim_scrollback();
+    }
+
+    fn trim_scrollback(&mut self) {
+        while self.scrollback.len() > self.scrollback_limit {
+            self.scrollback.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    // scroll the scroll region down, blank lines coming in at its top
+    fn scroll_down(&mut self, n: usize) {
+        let n = n.min(self.bottom - self.top + 1);
+        for _ in 0..n {
+            self.lines.remove(self.bottom);
+            let blank = self.blank_line();
+            self.lines.insert(self.top, blank);
+        }
+    }
+
+    fn insert_lines(&mut self, n: usize) {
+        if (self.top..=self.bottom).contains(&self.cursor.row) {
+            let top = std::mem::replace(&mut self.top, self.cursor.row);
+            self.scroll_down(n);
+            self.top = top;
+            self.cursor.col = 0;
+        }
+    }
+
+    fn delete_lines(&mut self, n: usize) {
+        let row = self.cursor.row;
+        if (self.top..=self.bottom).contains(&row) {
+            for _ in 0..n.min(self.bottom - row + 1) {
+                self.lines.remove(row);
+                let blank = self.blank_line();
+                self.lines.insert(self.bottom, blank);
+            }
+            self.cursor.col = 0;
+        }
+    }
+
+    fn insert_chars(&mut self, n: usize) {
+        let blank = self.blank();
+        let col = self.cursor.col;
+        let line = &mut self.lines[self.cursor.row];
+        let n = n.min(line.len() - col);
+        line.truncate(line.len() - n);
+        line.splice(col..col, std::iter::repeat_n(blank, n));
+        self.cursor.wrap_pending = false;
+    }
+
+    fn delete_chars(&mut self, n: usize) {
+        let blank = self.blank();
+        let col = self.cursor.col;
+        let line = &mut self.lines[self.cursor.row];
+        let n = n.min(line.len() - col);
+        line.drain(col..col + n);
+        line.extend(std::iter::repeat_n(blank, n));
+        self.cursor.wrap_pending = false;
+    }
+
+    fn erase_chars(&mut self, n: usize) {
+        let blank = self.blank();
+        let col = self.cursor.col;
+        let line = &mut self.lines[self.cursor.row];
+        let end = (col + n).min(line.len());
+        line[col..end].fill(blank);
+        self.cursor.wrap_pending = false;
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let blank = self.blank();
+        let col = self.cursor.col;
+        let line = &mut self.lines[self.cursor.row];
+        match mode {
+            0 => line[col..].fill(blank),
+            1 => line[..=col].fill(blank),
+            _ => line.fill(blank),
+        }
+        self.cursor.wrap_pending = false;
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        let blank = self.blank();
+        let row = self.cursor.row;
+        match mode {
+            0 => {
+                self.erase_line(0);
+                for line in &mut self.lines[row + 1..] {
+                    line.fill(blank);
+                }
+            }
+            1 => {
+                self.erase_line(1);
+                for line in &mut self.lines[..row] {
+                    line.fill(blank);
+                }
+            }
+            2 => {
+                for line in &mut self.lines {
+                    line.fill(blank);
+                }
+            }
+            3 => {
+                self.dropped += self.scrollback.len();
+                self.scrollback.clear();
+            }
+            _ => (),
+        }
+    }
+
+    fn tab_forward(&mut self, n: usize) {
+        for _ in 0..n {
+            let next = (self.cursor.col + 1..self.cols)
+                .find(|&c| self.tabs[c])
+                .unwrap_or(self.cols - 1);
+            self.cursor.col = next;
+        }
+        self.cursor.wrap_pending = false;
+    }
+
+    fn tab_backward(&mut self, n: usize) {
+        for _ in 0..n {
+            self.cursor.col = (0..self.cursor.col)
+                .rev()
+                .find(|&c| self.tabs[c])
+                .unwrap_or(0);
+        }
+        self.cursor.wrap_pending = false;
+    }
+
+    fn move_up(&mut self, n: usize) {
+        let limit = if self.cursor.row >= self.top {
+            self.top
+        } else {
+            0
+        };
+        self.cursor.row = self.cursor.row.saturating_sub(n).max(limit);
+        self.cursor.wrap_pending = false;
+    }
+
+    fn move_down(&mut self, n: usize) {
+        let limit = if self.cursor.row <= self.bottom {
+            self.bottom
+        } else {
+            self.rows - 1
+        };
+        self.cursor.row = (self.cursor.row + n).min(limit);
+        self.cursor.wrap_pending = false;
+    }
+
+    // move within the screen, ignoring the origin mode
+    fn move_to(&mut self, row: usize, col: usize) {
+        self.cursor.row = row.min(self.rows - 1);
+        self.cursor.col = col.min(self.cols - 1);
+        self.cursor.wrap_pending = false;
+    }
+
+    // move to a position, relative to the scroll region in origin mode
+    fn goto(&mut self, row: usize, col: usize) {
+        if self.cursor.origin {
+            self.move_to((self.top + row).min(self.bottom), col);
+        } else {
+            self.move_to(row, col);
+        }
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved = self.cursor;
+    }
+
+    fn restore_cursor(&mut self) {
+        self.cursor = self.saved;
+        self.modes.origin = self.cursor.origin;
+        // the scroll region may have changed since the cursor was saved
+        let row = if self.cursor.origin {
+            self.cursor.row.clamp(self.top, self.bottom)
+        } else {
+            self.cursor.row
+        };
+        self.move_to(row, self.cursor.col);
+    }
+
+    fn switch_screen(&mut self, alt: bool) {
+        if alt != self.alt_screen {
+            std::mem::swap(&mut self.lines, &mut self.other_lines);
+            std::mem::swap(&mut self.saved, &mut self.other_saved);
+            self.alt_screen = alt;
+        }
+    }
+
+    // RIS, back to the state of a new terminal
+    fn reset(&mut self) {
+        let mut vt = Self::new(self.cols, self.rows);
+        vt.title = std::mem::take(&mut self.title);
+        vt.scrollback_limit = self.scrollback_limit;
+        // the cleared lines count as dropped so the lines after them keep increasing numbers
+        vt.dropped = self.dropped + self.scrollback.len() + self.rows;
+        *self = vt;
+    }
+}
+
+// the colors of 38 and 48 SGR parameters, after the 38 or 48.
+// the colon form has an extra color space id before the RGB values
+fn extended_color(p: &[u16], colon: bool) -> Option<Color> {
+    match p.first()? {
+        5 => Some(Color::Indexed(*p.get(1)?.min(&255) as u8)),
+        2 => {
+            let rgb = if colon && p.len() > 4 {
+                &p[2..5]
+            } else {
+                p.get(1..4)?
+            };
+            let v = |i: usize| rgb[i].min(255) as u8;
+            Some(Color::Rgb(v(0), v(1), v(2)))
+        }
+        _ => None,
+    }
+}
+
+// the DEC special graphics set, used for box drawing
+fn line_drawing(ch: char) -> char {
+    match ch {
+        '`' => '◆',
+        'a' => '▒',
+        'f' => '°',
+        'g' => '±',
+        'j' => '┘',
+        'k' => '┐',
+        'l' => '┌',
+        'm' => '└',
+        'n' => '┼',
+        'o' => '⎺',
+        'p' => '⎻',
+        'q' => '─',
+        'r' => '⎼',
+        's' => '⎽',
+        't' => '├',
+        'u' => '┤',
+        'v' => '┴',
+        'w' => '┬',
+        'x' => '│',
+        'y' => '≤',
+        'z' => '≥',
+        '{' => 'π',
+        '|' => '≠',
+        '}' => '£',
+        '~' => '·',
+        _ => ch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the text of a row of the screen, without trailing spaces
+    fn row(vt: &Terminal, row: usize) -> String {
+        let line = vt.history_line(vt.screen_top() + row).unwrap_or_default();
+        let text: String = line.iter().map(|c| c.ch).collect();
+        text.trim_end().to_string()
+    }
+
+    fn cell(vt: &Terminal, row: usize, col: usize) -> Cell {
+        vt.history_line(vt.screen_top() + row).unwrap()[col]
+    }
+
+    fn screen(cols: usize, rows: usize, output: &str) -> Terminal {
+        let mut vt = Terminal::new(cols, rows);
+        vt.feed(output.as_bytes());
+        vt
+    }
+
+    #[test]
+    fn cursor_position() {
+        let mut vt = screen(10, 5, "\x1b[3;4Hx");
+        assert_eq!(row(&vt, 2), "   x");
+        assert_eq!(vt.cursor(), (2, 4));
+        // positions past the screen stop at its edges
+        vt.feed(b"\x1b[99;99H");
+        assert_eq!(vt.cursor(), (4, 9));
+        vt.feed(b"\x1b[H");
+        assert_eq!(vt.cursor(), (0, 0));
+    }
+
+    #[test]
+    fn origin_mode() {
+        let mut vt = screen(10, 6, "\x1b[2;4r\x1b[?6h\x1b[1;1Hx\x1b[9;1Hy");
+        assert_eq!(row(&vt, 1), "x");
+        // clamped to the bottom of the scroll region
+        assert_eq!(row(&vt, 3), "y");
+        vt.feed(b"\x1b[6n");
+        assert_eq!(vt.take_replies(), b"\x1b[3;2R");
+    }
+
+    #[test]
+    fn cursor_report_after_scroll_region_change() {
+        // the cursor saved above the new region is restored into it
+        let mut vt = screen(80, 24, "\x1b[?6h\x1b7\x1b[5;20r\x1b8\x1b[6n");
+        assert_eq!(vt.take_replies(), b"\x1b[1;1R");
+        assert_eq!(vt.cursor(), (4, 0));
+    }
+
+    #[test]
+    fn scroll_region() {
+        let mut vt = screen(10, 5, "1\r\n2\r\n3\r\n4\r\n5");
+        // line feeds at the bottom of the region only scroll the region
+        vt.feed(b"\x1b[2;4r\x1b[4;1H\nx");
+        let rows: Vec<String> = (0..5).map(|r| row(&vt, r)).collect();
+        assert_eq!(rows, ["1", "3", "4", "x", "5"]);
+        // lines scrolled within a region don't go to the scrollback
+        assert_eq!(vt.screen_top(), vt.first_line());
+    }
+
+    #[test]
+    fn scrollback() {
+        let mut vt = Terminal::new(10, 2);
+        vt.set_scrollback_limit(1);
+        vt.feed(b"1\r\n2\r\n3\r\n4");
+        assert_eq!(vt.screen_top() - vt.first_line(), 1);
+        assert_eq!(vt.text_range((vt.first_line(), 0), (vt.screen_top() + 1, 9)), "2\n3\n4");
+    }
+
+    #[test]
+    fn erase() {
+        let mut vt = screen(5, 3, "abcde\r\nfghij\r\nklmno\x1b[2;3H\x1b[K");
+        assert_eq!(row(&vt, 1), "fg");
+        vt.feed(b"\x1b[1K");
+        assert_eq!(row(&vt, 1), "");
+        vt.feed(b"\x1b[J");
+        assert_eq!(row(&vt, 0), "abcde");
+        assert_eq!(row(&vt, 2), "");
+        vt.feed(b"\x1b[1J");
+        assert_eq!(row(&vt, 0), "");
+        vt.feed(b"klm\x1b[2J");
+        assert!((0..3).all(|r| row(&vt, r).is_empty()));
+    }
+
+    #[test]
+    fn alternate_screen() {
+        let mut vt = screen(10, 3, "shell\x1b[?1049h");
+        assert!(vt.alt_screen());
+        assert_eq!(row(&vt, 0), "");
+        vt.feed(b"\x1b[Hfull");
+        assert_eq!(row(&vt, 0), "full");
+        vt.feed(b"\x1b[?1049l");
+        assert!(!vt.alt_screen());
+        assert_eq!(row(&vt, 0), "shell");
+        assert_eq!(vt.cursor(), (0, 5));
+    }
+
+    #[test]
+    fn sgr() {
+        let vt = screen(10, 1, "\x1b[1;4;31ma\x1b[38;5;200;48;2;1;2;3mb\x1b[22;39mc\x1b[0md");
+        let a = cell(&vt, 0, 0).attrs;
+        assert!(a.bold && a.underline);
+        assert_eq!(a.fg, Color::Indexed(1));
+        let b = cell(&vt, 0, 1).attrs;
+        assert_eq!(b.fg, Color::Indexed(200));
+        assert_eq!(b.bg, Color::Rgb(1, 2, 3));
+        let c = cell(&vt, 0, 2).attrs;
+        assert!(!c.bold && c.underline);
+        assert_eq!(c.fg, Color::Default);
+        assert_eq!(cell(&vt, 0, 3).attrs, Attrs::default());
+    }
+}